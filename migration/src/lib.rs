@@ -0,0 +1,22 @@
+pub use sea_orm_migration::prelude::*;
+
+mod m20250101_000001_create_base_tables;
+mod m20250101_000002_create_auth_tables;
+mod m20250101_000003_create_oauth_tables;
+mod m20250101_000004_seed_data;
+mod m20250101_000005_department_path;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![
+            Box::new(m20250101_000001_create_base_tables::Migration),
+            Box::new(m20250101_000002_create_auth_tables::Migration),
+            Box::new(m20250101_000003_create_oauth_tables::Migration),
+            Box::new(m20250101_000004_seed_data::Migration),
+            Box::new(m20250101_000005_department_path::Migration),
+        ]
+    }
+}
@@ -0,0 +1,308 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// 部门、用户、角色、菜单及其关联表
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Department::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Department::Id))
+                    .col(integer_null(Department::FatherId))
+                    .col(string_null(Department::DepartmentName))
+                    .col(integer_null(Department::OrderNum))
+                    .col(boolean_null(Department::State))
+                    .col(timestamp_null(Department::UpdatedAt))
+                    .col(timestamp(Department::CreatedAt))
+                    .col(timestamp_null(Department::DeletedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Role::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Role::Id))
+                    .col(string(Role::RoleName))
+                    .col(string_null(Role::RoleDesc))
+                    .col(boolean(Role::RequireMfa).default(false))
+                    .col(timestamp_null(Role::UpdatedAt))
+                    .col(timestamp(Role::CreatedAt))
+                    .col(timestamp_null(Role::DeletedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Menu::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Menu::Id))
+                    .col(integer(Menu::FatherId).default(0))
+                    .col(string(Menu::MenuName))
+                    .col(string(Menu::MenuType))
+                    .col(string_null(Menu::AlIcon))
+                    .col(string_null(Menu::Icon))
+                    .col(string_null(Menu::Path))
+                    .col(string(Menu::Code))
+                    .col(integer(Menu::OrderNum).default(0))
+                    .col(boolean_null(Menu::Status))
+                    .col(boolean_null(Menu::NewLinkFlag))
+                    .col(boolean_null(Menu::Visible))
+                    .col(timestamp_null(Menu::UpdatedAt))
+                    .col(timestamp(Menu::CreatedAt))
+                    .col(timestamp_null(Menu::DeletedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(User::Table)
+                    .if_not_exists()
+                    .col(pk_auto(User::Id))
+                    .col(string_null(User::Email))
+                    .col(string(User::UserName))
+                    .col(string(User::Password))
+                    .col(boolean(User::Available).default(true))
+                    .col(boolean(User::ServiceAccount).default(false))
+                    .col(string_null(User::AuthProvider))
+                    .col(integer(User::Sex).default(0))
+                    .col(string(User::Mobile).default(""))
+                    .col(string_null(User::Telephone))
+                    .col(integer(User::DepartmentId))
+                    .col(timestamp_null(User::LastLoginTime))
+                    .col(string_null(User::LastLoginIp))
+                    .col(timestamp_null(User::PasswordChangedAt))
+                    .col(timestamp_null(User::UpdatedAt))
+                    .col(timestamp(User::CreatedAt))
+                    .col(timestamp_null(User::DeletedAt))
+                    // 有用户的部门不能删除
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_department_id")
+                            .from(User::Table, User::DepartmentId)
+                            .to(Department::Table, Department::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysUserRole::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysUserRole::Id))
+                    .col(integer(SysUserRole::RoleId))
+                    .col(integer(SysUserRole::UserId))
+                    .col(timestamp_null(SysUserRole::UpdatedAt))
+                    .col(timestamp(SysUserRole::CreatedAt))
+                    .col(timestamp_null(SysUserRole::DeletedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_sys_user_role_user_id")
+                            .from(SysUserRole::Table, SysUserRole::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_sys_user_role_role_id")
+                            .from(SysUserRole::Table, SysUserRole::RoleId)
+                            .to(Role::Table, Role::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysRolePerm::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysRolePerm::Id))
+                    .col(integer(SysRolePerm::RoleId))
+                    .col(string(SysRolePerm::PermCode))
+                    .col(timestamp_null(SysRolePerm::UpdatedAt))
+                    .col(timestamp(SysRolePerm::CreatedAt))
+                    .col(timestamp_null(SysRolePerm::DeletedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_sys_role_perm_role_id")
+                            .from(SysRolePerm::Table, SysRolePerm::RoleId)
+                            .to(Role::Table, Role::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 表已存在时 create_table 不会补上后来新增的列
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Role::Table)
+                    .add_column_if_not_exists(boolean(Role::RequireMfa).default(false))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column_if_not_exists(boolean(User::ServiceAccount).default(false))
+                    .add_column_if_not_exists(string_null(User::AuthProvider))
+                    .add_column_if_not_exists(string_null(User::LastLoginIp))
+                    .add_column_if_not_exists(timestamp_null(User::PasswordChangedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 回收站中的记录仍占用用户名和菜单编码，恢复时不会冲突
+        create_index(manager, "uk_user_user_name", User::Table, &[User::UserName], true).await?;
+        create_index(manager, "uk_menu_code", Menu::Table, &[Menu::Code], true).await?;
+        create_index(manager, "idx_sys_user_role_user_id", SysUserRole::Table, &[SysUserRole::UserId], false).await?;
+        create_index(manager, "idx_sys_user_role_role_id", SysUserRole::Table, &[SysUserRole::RoleId], false).await?;
+        create_index(manager, "idx_sys_role_perm_role_id", SysRolePerm::Table, &[SysRolePerm::RoleId], false).await?;
+
+        create_index(manager, "idx_department_deleted_at", Department::Table, &[Department::DeletedAt], false).await?;
+        create_index(manager, "idx_role_deleted_at", Role::Table, &[Role::DeletedAt], false).await?;
+        create_index(manager, "idx_menu_deleted_at", Menu::Table, &[Menu::DeletedAt], false).await?;
+        create_index(manager, "idx_user_deleted_at", User::Table, &[User::DeletedAt], false).await?;
+        create_index(manager, "idx_sys_user_role_deleted_at", SysUserRole::Table, &[SysUserRole::DeletedAt], false).await?;
+        create_index(manager, "idx_sys_role_perm_deleted_at", SysRolePerm::Table, &[SysRolePerm::DeletedAt], false).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(SysRolePerm::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysUserRole::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(User::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(Menu::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(Role::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(Department::Table).if_exists().to_owned()).await?;
+        Ok(())
+    }
+}
+
+pub(crate) async fn create_index<T, C>(manager: &SchemaManager<'_>, name: &str, table: T, cols: &[C], unique: bool) -> Result<(), DbErr>
+where
+    T: IntoIden + 'static,
+    C: IntoIden + Copy + 'static,
+{
+    let mut index = Index::create();
+    index.name(name).table(table).if_not_exists();
+    for col in cols {
+        index.col(*col);
+    }
+    if unique {
+        index.unique();
+    }
+    manager.create_index(index.to_owned()).await
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Department {
+    Table,
+    Id,
+    FatherId,
+    DepartmentName,
+    OrderNum,
+    State,
+    Path,
+    UpdatedAt,
+    CreatedAt,
+    DeletedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Role {
+    Table,
+    Id,
+    RoleName,
+    RoleDesc,
+    RequireMfa,
+    UpdatedAt,
+    CreatedAt,
+    DeletedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Menu {
+    Table,
+    Id,
+    FatherId,
+    MenuName,
+    MenuType,
+    AlIcon,
+    Icon,
+    Path,
+    Code,
+    OrderNum,
+    Status,
+    NewLinkFlag,
+    Visible,
+    UpdatedAt,
+    CreatedAt,
+    DeletedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum User {
+    Table,
+    Id,
+    Email,
+    UserName,
+    Password,
+    Available,
+    ServiceAccount,
+    AuthProvider,
+    Sex,
+    Mobile,
+    Telephone,
+    DepartmentId,
+    LastLoginTime,
+    LastLoginIp,
+    PasswordChangedAt,
+    UpdatedAt,
+    CreatedAt,
+    DeletedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum SysUserRole {
+    Table,
+    Id,
+    RoleId,
+    UserId,
+    UpdatedAt,
+    CreatedAt,
+    DeletedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum SysRolePerm {
+    Table,
+    Id,
+    RoleId,
+    PermCode,
+    UpdatedAt,
+    CreatedAt,
+    DeletedAt,
+}
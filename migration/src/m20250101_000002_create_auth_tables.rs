@@ -0,0 +1,316 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250101_000001_create_base_tables::{create_index, User};
+
+/// 登录、会话、密码、两步验证和 API key 相关的表，随用户一起删除
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysRefreshToken::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysRefreshToken::Id))
+                    .col(integer(SysRefreshToken::UserId))
+                    .col(string(SysRefreshToken::FamilyId))
+                    .col(string_uniq(SysRefreshToken::TokenHash))
+                    .col(timestamp(SysRefreshToken::ExpiresAt))
+                    .col(timestamp_null(SysRefreshToken::UsedAt))
+                    .col(timestamp_null(SysRefreshToken::RevokedAt))
+                    .col(timestamp_null(SysRefreshToken::UpdatedAt))
+                    .col(timestamp(SysRefreshToken::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_refresh_token_user_id", SysRefreshToken::Table, SysRefreshToken::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysRevokedToken::Table)
+                    .if_not_exists()
+                    .col(string(SysRevokedToken::Jti).primary_key())
+                    .col(integer(SysRevokedToken::UserId))
+                    .col(timestamp(SysRevokedToken::ExpiresAt))
+                    .col(timestamp(SysRevokedToken::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_revoked_token_user_id", SysRevokedToken::Table, SysRevokedToken::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysLoginAttempt::Table)
+                    .if_not_exists()
+                    .col(string(SysLoginAttempt::Key).primary_key())
+                    .col(integer(SysLoginAttempt::Failures).default(0))
+                    .col(timestamp_null(SysLoginAttempt::LockedUntil))
+                    .col(timestamp_null(SysLoginAttempt::LastFailedAt))
+                    .col(timestamp_null(SysLoginAttempt::UpdatedAt))
+                    .col(timestamp(SysLoginAttempt::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysPasswordHistory::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysPasswordHistory::Id))
+                    .col(integer(SysPasswordHistory::UserId))
+                    .col(string(SysPasswordHistory::PasswordHash))
+                    .col(timestamp(SysPasswordHistory::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_password_history_user_id", SysPasswordHistory::Table, SysPasswordHistory::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysPasswordResetToken::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysPasswordResetToken::Id))
+                    .col(integer(SysPasswordResetToken::UserId))
+                    .col(string_uniq(SysPasswordResetToken::TokenHash))
+                    .col(timestamp(SysPasswordResetToken::ExpiresAt))
+                    .col(timestamp_null(SysPasswordResetToken::UsedAt))
+                    .col(timestamp(SysPasswordResetToken::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_password_reset_token_user_id", SysPasswordResetToken::Table, SysPasswordResetToken::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysUserMfa::Table)
+                    .if_not_exists()
+                    .col(integer(SysUserMfa::UserId).primary_key())
+                    .col(string(SysUserMfa::Secret))
+                    .col(timestamp_null(SysUserMfa::EnabledAt))
+                    .col(big_integer_null(SysUserMfa::LastUsedStep))
+                    .col(timestamp_null(SysUserMfa::UpdatedAt))
+                    .col(timestamp(SysUserMfa::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_user_mfa_user_id", SysUserMfa::Table, SysUserMfa::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysMfaRecoveryCode::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysMfaRecoveryCode::Id))
+                    .col(integer(SysMfaRecoveryCode::UserId))
+                    .col(string(SysMfaRecoveryCode::CodeHash))
+                    .col(timestamp_null(SysMfaRecoveryCode::UsedAt))
+                    .col(timestamp(SysMfaRecoveryCode::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_mfa_recovery_code_user_id", SysMfaRecoveryCode::Table, SysMfaRecoveryCode::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysUserSession::Table)
+                    .if_not_exists()
+                    .col(string(SysUserSession::Id).primary_key())
+                    .col(integer(SysUserSession::UserId))
+                    .col(string_null(SysUserSession::Device))
+                    .col(string_null(SysUserSession::Ip))
+                    .col(string_null(SysUserSession::UserAgent))
+                    .col(timestamp_null(SysUserSession::LastSeenAt))
+                    .col(timestamp(SysUserSession::ExpiresAt))
+                    .col(timestamp_null(SysUserSession::RevokedAt))
+                    .col(timestamp_null(SysUserSession::UpdatedAt))
+                    .col(timestamp(SysUserSession::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_user_session_user_id", SysUserSession::Table, SysUserSession::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysApiKey::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysApiKey::Id))
+                    .col(integer(SysApiKey::UserId))
+                    .col(string(SysApiKey::Name))
+                    .col(string_uniq(SysApiKey::Prefix))
+                    .col(string(SysApiKey::KeyHash))
+                    .col(json(SysApiKey::Scopes))
+                    .col(json_null(SysApiKey::IpAllowList))
+                    .col(timestamp_null(SysApiKey::ExpiresAt))
+                    .col(timestamp_null(SysApiKey::LastUsedAt))
+                    .col(string_null(SysApiKey::LastUsedIp))
+                    .col(timestamp_null(SysApiKey::RevokedAt))
+                    .col(integer_null(SysApiKey::CreatedBy))
+                    .col(timestamp_null(SysApiKey::UpdatedAt))
+                    .col(timestamp(SysApiKey::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_api_key_user_id", SysApiKey::Table, SysApiKey::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_sys_api_key_created_by")
+                            .from(SysApiKey::Table, SysApiKey::CreatedBy)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        create_index(manager, "idx_sys_refresh_token_user_id", SysRefreshToken::Table, &[SysRefreshToken::UserId], false).await?;
+        create_index(manager, "idx_sys_refresh_token_family_id", SysRefreshToken::Table, &[SysRefreshToken::FamilyId], false).await?;
+        create_index(manager, "idx_sys_revoked_token_expires_at", SysRevokedToken::Table, &[SysRevokedToken::ExpiresAt], false).await?;
+        create_index(manager, "idx_sys_password_history_user_id", SysPasswordHistory::Table, &[SysPasswordHistory::UserId], false).await?;
+        create_index(manager, "idx_sys_mfa_recovery_code_user_id", SysMfaRecoveryCode::Table, &[SysMfaRecoveryCode::UserId], false).await?;
+        create_index(manager, "idx_sys_user_session_user_id", SysUserSession::Table, &[SysUserSession::UserId], false).await?;
+        create_index(manager, "idx_sys_api_key_user_id", SysApiKey::Table, &[SysApiKey::UserId], false).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(SysApiKey::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysUserSession::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysMfaRecoveryCode::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysUserMfa::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysPasswordResetToken::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysPasswordHistory::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysLoginAttempt::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysRevokedToken::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysRefreshToken::Table).if_exists().to_owned()).await?;
+        Ok(())
+    }
+}
+
+/// 删除用户时一起删除
+pub(crate) fn user_fk<T, C>(name: &str, table: T, col: C) -> ForeignKeyCreateStatement
+where
+    T: IntoIden + 'static,
+    C: IntoIden + 'static,
+{
+    ForeignKey::create()
+        .name(name)
+        .from(table, col)
+        .to(User::Table, User::Id)
+        .on_delete(ForeignKeyAction::Cascade)
+        .to_owned()
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysRefreshToken {
+    Table,
+    Id,
+    UserId,
+    FamilyId,
+    TokenHash,
+    ExpiresAt,
+    UsedAt,
+    RevokedAt,
+    UpdatedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysRevokedToken {
+    Table,
+    Jti,
+    UserId,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum SysLoginAttempt {
+    Table,
+    Key,
+    Failures,
+    LockedUntil,
+    LastFailedAt,
+    UpdatedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysPasswordHistory {
+    Table,
+    Id,
+    UserId,
+    PasswordHash,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum SysPasswordResetToken {
+    Table,
+    Id,
+    UserId,
+    TokenHash,
+    ExpiresAt,
+    UsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum SysUserMfa {
+    Table,
+    UserId,
+    Secret,
+    EnabledAt,
+    LastUsedStep,
+    UpdatedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysMfaRecoveryCode {
+    Table,
+    Id,
+    UserId,
+    CodeHash,
+    UsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysUserSession {
+    Table,
+    Id,
+    UserId,
+    Device,
+    Ip,
+    UserAgent,
+    LastSeenAt,
+    ExpiresAt,
+    RevokedAt,
+    UpdatedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysApiKey {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Prefix,
+    KeyHash,
+    Scopes,
+    IpAllowList,
+    ExpiresAt,
+    LastUsedAt,
+    LastUsedIp,
+    RevokedAt,
+    CreatedBy,
+    UpdatedAt,
+    CreatedAt,
+}
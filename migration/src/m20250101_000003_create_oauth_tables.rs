@@ -0,0 +1,229 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250101_000001_create_base_tables::create_index;
+use crate::m20250101_000002_create_auth_tables::user_fk;
+
+/// OAuth2 客户端和授权、单点登录身份以及审计日志
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysOauthClient::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysOauthClient::Id))
+                    .col(string_uniq(SysOauthClient::ClientId))
+                    .col(string_null(SysOauthClient::ClientSecretHash))
+                    .col(string(SysOauthClient::Name))
+                    .col(json(SysOauthClient::RedirectUris))
+                    .col(json(SysOauthClient::GrantTypes))
+                    .col(json(SysOauthClient::Scopes))
+                    .col(boolean(SysOauthClient::Available).default(true))
+                    .col(timestamp_null(SysOauthClient::UpdatedAt))
+                    .col(timestamp(SysOauthClient::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysOauthCode::Table)
+                    .if_not_exists()
+                    .col(string(SysOauthCode::CodeHash).primary_key())
+                    .col(string(SysOauthCode::ClientId))
+                    .col(integer(SysOauthCode::UserId))
+                    .col(string(SysOauthCode::RedirectUri))
+                    .col(string(SysOauthCode::Scope))
+                    .col(string_null(SysOauthCode::CodeChallenge))
+                    .col(string_null(SysOauthCode::CodeChallengeMethod))
+                    .col(string_null(SysOauthCode::Nonce))
+                    .col(timestamp(SysOauthCode::ExpiresAt))
+                    .col(timestamp_null(SysOauthCode::UsedAt))
+                    .col(timestamp(SysOauthCode::CreatedAt))
+                    .foreign_key(&mut client_fk("fk_sys_oauth_code_client_id", SysOauthCode::Table, SysOauthCode::ClientId))
+                    .foreign_key(&mut user_fk("fk_sys_oauth_code_user_id", SysOauthCode::Table, SysOauthCode::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysOauthToken::Table)
+                    .if_not_exists()
+                    .col(string(SysOauthToken::Jti).primary_key())
+                    .col(string(SysOauthToken::ClientId))
+                    .col(integer_null(SysOauthToken::UserId))
+                    .col(string(SysOauthToken::Scope))
+                    .col(timestamp(SysOauthToken::ExpiresAt))
+                    .col(timestamp_null(SysOauthToken::RevokedAt))
+                    .col(timestamp(SysOauthToken::CreatedAt))
+                    .foreign_key(&mut client_fk("fk_sys_oauth_token_client_id", SysOauthToken::Table, SysOauthToken::ClientId))
+                    .foreign_key(&mut user_fk("fk_sys_oauth_token_user_id", SysOauthToken::Table, SysOauthToken::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysUserIdentity::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysUserIdentity::Id))
+                    .col(integer(SysUserIdentity::UserId))
+                    .col(string(SysUserIdentity::Provider))
+                    .col(string(SysUserIdentity::Subject))
+                    .col(string_null(SysUserIdentity::Email))
+                    .col(timestamp_null(SysUserIdentity::LastLoginAt))
+                    .col(timestamp(SysUserIdentity::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_user_identity_user_id", SysUserIdentity::Table, SysUserIdentity::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysOidcLoginState::Table)
+                    .if_not_exists()
+                    .col(string(SysOidcLoginState::StateHash).primary_key())
+                    .col(string(SysOidcLoginState::Nonce))
+                    .col(string(SysOidcLoginState::CodeVerifier))
+                    .col(integer_null(SysOidcLoginState::LinkUserId))
+                    .col(timestamp(SysOidcLoginState::ExpiresAt))
+                    .col(timestamp(SysOidcLoginState::CreatedAt))
+                    .foreign_key(&mut user_fk("fk_sys_oidc_login_state_link_user_id", SysOidcLoginState::Table, SysOidcLoginState::LinkUserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 审计日志不随用户删除
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysAuditLog::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SysAuditLog::Id))
+                    .col(integer(SysAuditLog::ActorId))
+                    .col(string(SysAuditLog::Action))
+                    .col(integer_null(SysAuditLog::TargetId))
+                    .col(text_null(SysAuditLog::Detail))
+                    .col(string_null(SysAuditLog::Ip))
+                    .col(timestamp(SysAuditLog::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        create_index(manager, "uk_sys_user_identity_provider_subject", SysUserIdentity::Table, &[SysUserIdentity::Provider, SysUserIdentity::Subject], true).await?;
+        create_index(manager, "idx_sys_user_identity_user_id", SysUserIdentity::Table, &[SysUserIdentity::UserId], false).await?;
+        create_index(manager, "idx_sys_oauth_token_client_id", SysOauthToken::Table, &[SysOauthToken::ClientId], false).await?;
+        create_index(manager, "idx_sys_audit_log_actor_id", SysAuditLog::Table, &[SysAuditLog::ActorId], false).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(SysAuditLog::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysOidcLoginState::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysUserIdentity::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysOauthToken::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysOauthCode::Table).if_exists().to_owned()).await?;
+        manager.drop_table(Table::drop().table(SysOauthClient::Table).if_exists().to_owned()).await?;
+        Ok(())
+    }
+}
+
+/// 删除客户端时一起删除其授权码和 token
+fn client_fk<T, C>(name: &str, table: T, col: C) -> ForeignKeyCreateStatement
+where
+    T: IntoIden + 'static,
+    C: IntoIden + 'static,
+{
+    ForeignKey::create()
+        .name(name)
+        .from(table, col)
+        .to(SysOauthClient::Table, SysOauthClient::ClientId)
+        .on_delete(ForeignKeyAction::Cascade)
+        .to_owned()
+}
+
+#[derive(DeriveIden)]
+enum SysOauthClient {
+    Table,
+    Id,
+    ClientId,
+    ClientSecretHash,
+    Name,
+    RedirectUris,
+    GrantTypes,
+    Scopes,
+    Available,
+    UpdatedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum SysOauthCode {
+    Table,
+    CodeHash,
+    ClientId,
+    UserId,
+    RedirectUri,
+    Scope,
+    CodeChallenge,
+    CodeChallengeMethod,
+    Nonce,
+    ExpiresAt,
+    UsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysOauthToken {
+    Table,
+    Jti,
+    ClientId,
+    UserId,
+    Scope,
+    ExpiresAt,
+    RevokedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysUserIdentity {
+    Table,
+    Id,
+    UserId,
+    Provider,
+    Subject,
+    Email,
+    LastLoginAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum SysOidcLoginState {
+    Table,
+    StateHash,
+    Nonce,
+    CodeVerifier,
+    LinkUserId,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum SysAuditLog {
+    Table,
+    Id,
+    ActorId,
+    Action,
+    TargetId,
+    Detail,
+    Ip,
+    CreatedAt,
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use argon2::{Argon2, PasswordHasher};
+use argon2::password_hash::SaltString;
+use rand::distributions::{Alphanumeric, DistString};
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement, StatementBuilder};
+use crate::m20250101_000001_create_base_tables::{Department, Menu, Role, SysRolePerm, SysUserRole, User};
+
+const ADMIN_USER_NAME: &str = "admin";
+
+/// 菜单类型：C 菜单，F 按钮
+const MENU: &str = "C";
+const BUTTON: &str = "F";
+
+/// (上级编码, 名称, 类型, 图标, 路由, 编码)
+type SeedMenu = (&'static str, &'static str, &'static str, Option<&'static str>, Option<&'static str>, &'static str);
+
+/// ng-antd-admin 的默认菜单
+const MENUS: &[SeedMenu] = &[
+    ("", "Dashboard", MENU, Some("dashboard"), Some("/default/dashboard"), "default:dashboard"),
+    ("default:dashboard", "分析页", MENU, None, Some("/default/dashboard/analysis"), "default:dashboard:analysis"),
+    ("default:dashboard", "监控页", MENU, None, Some("/default/dashboard/monitor"), "default:dashboard:monitor"),
+    ("default:dashboard", "工作台", MENU, None, Some("/default/dashboard/workbench"), "default:dashboard:workbench"),
+    ("", "系统管理", MENU, Some("setting"), Some("/default/system"), "default:system"),
+    ("default:system", "菜单管理", MENU, None, Some("/default/system/menu"), "default:system:menu"),
+    ("default:system:menu", "新增", BUTTON, None, None, "default:system:menu:add"),
+    ("default:system:menu", "编辑", BUTTON, None, None, "default:system:menu:edit"),
+    ("default:system:menu", "删除", BUTTON, None, None, "default:system:menu:del"),
+    ("default:system", "账号管理", MENU, None, Some("/default/system/account"), "default:system:account"),
+    ("default:system:account", "新增", BUTTON, None, None, "default:system:account:add"),
+    ("default:system:account", "编辑", BUTTON, None, None, "default:system:account:edit"),
+    ("default:system:account", "删除", BUTTON, None, None, "default:system:account:del"),
+    ("default:system:account", "模拟登录", BUTTON, None, None, "default:system:account:impersonate"),
+    ("default:system", "角色管理", MENU, None, Some("/default/system/role-manager"), "default:system:role-manager"),
+    ("default:system:role-manager", "新增", BUTTON, None, None, "default:system:role-manager:add"),
+    ("default:system:role-manager", "编辑", BUTTON, None, None, "default:system:role-manager:edit"),
+    ("default:system:role-manager", "删除", BUTTON, None, None, "default:system:role-manager:del"),
+    ("default:system:role-manager", "设置权限", BUTTON, None, None, "default:system:role-manager:set-role"),
+    ("default:system", "部门管理", MENU, None, Some("/default/system/dept"), "default:system:dept"),
+    ("default:system:dept", "新增", BUTTON, None, None, "default:system:dept:add"),
+    ("default:system:dept", "删除", BUTTON, None, None, "default:system:dept:del"),
+    ("default:system", "API Key", MENU, None, Some("/default/system/api-key"), "default:system:api-key"),
+    ("default:system:api-key", "新增", BUTTON, None, None, "default:system:api-key:add"),
+    ("default:system:api-key", "删除", BUTTON, None, None, "default:system:api-key:del"),
+    ("default:system", "OAuth 客户端", MENU, None, Some("/default/system/oauth-client"), "default:system:oauth-client"),
+    ("default:system:oauth-client", "新增", BUTTON, None, None, "default:system:oauth-client:add"),
+    ("default:system:oauth-client", "删除", BUTTON, None, None, "default:system:oauth-client:del"),
+    ("default:system", "审计日志", MENU, None, Some("/default/system/audit-log"), "default:system:audit-log"),
+];
+
+/// 默认部门、超级管理员角色和 admin 用户以及菜单。
+/// 已有用户的数据库（从旧版本升级）不写入任何数据。
+/// admin 的初始密码取 `ADMIN_INITIAL_PASSWORD`，未设置时随机生成并写入 `ADMIN_PASSWORD_FILE`
+/// （默认 `admin-initial-password.txt`，权限 0600），不会打印到日志
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let count = db
+            .query_one(build(manager, Query::select().expr(Expr::col(User::Id).count()).from(User::Table).to_owned()))
+            .await?
+            .map(|row| row.try_get_by_index::<i64>(0))
+            .transpose()?
+            .unwrap_or(0);
+        if count > 0 {
+            return Ok(());
+        }
+
+        let department_id = insert_returning_id(
+            manager,
+            Query::insert()
+                .into_table(Department::Table)
+                .columns([Department::FatherId, Department::DepartmentName, Department::OrderNum, Department::State, Department::CreatedAt])
+                .values_panic([0.into(), "总部".into(), 0.into(), true.into(), Expr::current_timestamp().into()])
+                .returning_col(Department::Id)
+                .to_owned(),
+        )
+        .await?;
+
+        let role_id = insert_returning_id(
+            manager,
+            Query::insert()
+                .into_table(Role::Table)
+                .columns([Role::RoleName, Role::RoleDesc, Role::RequireMfa, Role::CreatedAt])
+                .values_panic(["超级管理员".into(), "拥有全部权限".into(), false.into(), Expr::current_timestamp().into()])
+                .returning_col(Role::Id)
+                .to_owned(),
+        )
+        .await?;
+
+        let mut menu_ids: HashMap<&str, i32> = HashMap::new();
+        for (index, (father_code, name, menu_type, icon, path, code)) in MENUS.iter().enumerate() {
+            let father_id = menu_ids.get(father_code).copied().unwrap_or(0);
+            let id = insert_returning_id(
+                manager,
+                Query::insert()
+                    .into_table(Menu::Table)
+                    .columns([
+                        Menu::FatherId, Menu::MenuName, Menu::MenuType, Menu::Icon, Menu::Path, Menu::Code,
+                        Menu::OrderNum, Menu::Status, Menu::NewLinkFlag, Menu::Visible, Menu::CreatedAt,
+                    ])
+                    .values_panic([
+                        father_id.into(), (*name).into(), (*menu_type).into(), (*icon).into(), (*path).into(), (*code).into(),
+                        (index as i32).into(), true.into(), false.into(), true.into(), Expr::current_timestamp().into(),
+                    ])
+                    .returning_col(Menu::Id)
+                    .to_owned(),
+            )
+            .await?;
+            menu_ids.insert(code, id);
+        }
+
+        let mut role_perms = Query::insert();
+        role_perms
+            .into_table(SysRolePerm::Table)
+            .columns([SysRolePerm::RoleId, SysRolePerm::PermCode, SysRolePerm::CreatedAt]);
+        for (_, _, _, _, _, code) in MENUS {
+            role_perms.values_panic([role_id.into(), (*code).into(), Expr::current_timestamp().into()]);
+        }
+        manager.exec_stmt(role_perms).await?;
+
+        let password = match env::var("ADMIN_INITIAL_PASSWORD") {
+            Ok(password) if !password.is_empty() => password,
+            _ => {
+                let password = Alphanumeric.sample_string(&mut rand::thread_rng(), 16);
+                // 不写入日志，只写到仅当前用户可读的文件
+                let path = env::var("ADMIN_PASSWORD_FILE").unwrap_or_else(|_| "admin-initial-password.txt".to_string());
+                write_secret(&path, &password)
+                    .map_err(|e| DbErr::Custom(format!("write {} failed: {}", path, e)))?;
+                log::warn!("created user {} with a generated password saved in {}, change it after the first sign-in and delete the file", ADMIN_USER_NAME, path);
+                password
+            }
+        };
+        let salt = SaltString::generate(rand::thread_rng());
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| DbErr::Migration(e.to_string()))?
+            .to_string();
+        let user_id = insert_returning_id(
+            manager,
+            Query::insert()
+                .into_table(User::Table)
+                .columns([
+                    User::UserName, User::Password, User::Available, User::ServiceAccount, User::Sex, User::Mobile,
+                    User::DepartmentId, User::PasswordChangedAt, User::CreatedAt,
+                ])
+                .values_panic([
+                    ADMIN_USER_NAME.into(), password_hash.into(), true.into(), false.into(), 0.into(), "".into(),
+                    department_id.into(), Expr::current_timestamp().into(), Expr::current_timestamp().into(),
+                ])
+                .returning_col(User::Id)
+                .to_owned(),
+        )
+        .await?;
+
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(SysUserRole::Table)
+                    .columns([SysUserRole::RoleId, SysUserRole::UserId, SysUserRole::CreatedAt])
+                    .values_panic([role_id.into(), user_id.into(), Expr::current_timestamp().into()])
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // 种子数据可能已被修改，回滚时保留
+        Ok(())
+    }
+}
+
+fn build<S: StatementBuilder>(manager: &SchemaManager, stmt: S) -> Statement {
+    manager.get_database_backend().build(&stmt)
+}
+
+async fn insert_returning_id(manager: &SchemaManager<'_>, stmt: InsertStatement) -> Result<i32, DbErr> {
+    manager
+        .get_connection()
+        .query_one(build(manager, stmt))
+        .await?
+        .ok_or_else(|| DbErr::Migration("insert returned no row".to_string()))?
+        .try_get_by_index(0)
+}
+
+fn write_secret(path: &str, secret: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    writeln!(file, "{}", secret)
+}
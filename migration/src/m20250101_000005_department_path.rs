@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+use crate::m20250101_000001_create_base_tables::Department;
+
+/// 部门的物化路径 `/1/3/7/`（包含自身），用于查询子树和检测移动时成环。
+/// 同时为已有的部门管理菜单补上编辑按钮，授予已有新增权限的角色
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Department::Table)
+                    .add_column_if_not_exists(string(Department::Path).default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        // 从根部门开始逐级拼接，上级缺失或成环的部门只包含自身
+        db.execute_unprepared(r#"UPDATE "department" SET "path" = '/' || "id" || '/'"#).await?;
+        db.execute_unprepared(
+            r#"WITH RECURSIVE "tree" ("id", "path") AS (
+                SELECT "id", '/' || "id" || '/' FROM "department" WHERE "father_id" IS NULL OR "father_id" = 0
+                UNION ALL
+                SELECT "d"."id", "t"."path" || "d"."id" || '/' FROM "department" "d" JOIN "tree" "t" ON "d"."father_id" = "t"."id"
+            )
+            UPDATE "department" SET "path" = "tree"."path" FROM "tree" WHERE "department"."id" = "tree"."id""#,
+        )
+        .await?;
+        // text_pattern_ops 使前缀 LIKE 可以走索引
+        db.execute_unprepared(r#"CREATE INDEX IF NOT EXISTS "idx_department_path" ON "department" ("path" text_pattern_ops)"#).await?;
+
+        db.execute_unprepared(
+            r#"INSERT INTO "menu" ("father_id", "menu_name", "menu_type", "code", "order_num", "status", "new_link_flag", "visible", "created_at")
+            SELECT "father_id", '编辑', 'F', 'default:system:dept:edit', "order_num", true, false, true, CURRENT_TIMESTAMP
+            FROM "menu" WHERE "code" = 'default:system:dept:add'
+            AND NOT EXISTS (SELECT 1 FROM "menu" WHERE "code" = 'default:system:dept:edit')"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"INSERT INTO "sys_role_perm" ("role_id", "perm_code", "created_at")
+            SELECT DISTINCT "role_id", 'default:system:dept:edit', CURRENT_TIMESTAMP
+            FROM "sys_role_perm" WHERE "perm_code" = 'default:system:dept:add' AND "deleted_at" IS NULL
+            AND "role_id" NOT IN (SELECT "role_id" FROM "sys_role_perm" WHERE "perm_code" = 'default:system:dept:edit')"#,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(Index::drop().name("idx_department_path").table(Department::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .alter_table(Table::alter().table(Department::Table).drop_column(Department::Path).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,26 @@
+use actix_web::{delete, post, Responder};
+use actix_web::web::{Data, Json, Path};
+use crate::{AppState, UserError};
+use crate::common::auth_user::AuthUser;
+use crate::common::perm_code;
+use crate::common::rbac::Perm;
+use crate::common::result::{CommonResult, FilterParam};
+use crate::service::api_key_service::{ApiKeyService, CreateApiKey, SearchApiKey};
+
+#[post("/list", wrap = "Perm::require(perm_code::API_KEY)")]
+pub async fn list(state:Data<AppState>,Json(page): Json<FilterParam<SearchApiKey>>)-> Result<impl Responder,UserError>{
+    let vec = ApiKeyService::find_all(state, page).await?;
+    Ok(CommonResult::success(vec))
+}
+
+#[post("/create", wrap = "Perm::require(perm_code::API_KEY_ADD)")]
+pub async fn create(state:Data<AppState>,user:AuthUser,Json(dto):Json<CreateApiKey>)->Result<impl Responder,UserError> {
+    let r = ApiKeyService::create(state, &user, dto).await?;
+    Ok(CommonResult::success(r))
+}
+
+#[delete("/{id}", wrap = "Perm::require(perm_code::API_KEY_DEL)")]
+pub async fn revoke(state:Data<AppState>,id:Path<i32>)->Result<impl Responder,UserError> {
+    ApiKeyService::revoke(state, id.into_inner()).await?;
+    Ok(CommonResult::<String>::success_none())
+}
@@ -0,0 +1,13 @@
+use actix_web::{post, Responder};
+use actix_web::web::{Data, Json};
+use crate::{AppState, UserError};
+use crate::common::perm_code;
+use crate::common::rbac::Perm;
+use crate::common::result::{CommonResult, FilterParam};
+use crate::service::audit_service::{AuditService, SearchAuditLog};
+
+#[post("/list", wrap = "Perm::require(perm_code::AUDIT_LOG)")]
+pub async fn list(state:Data<AppState>,Json(page): Json<FilterParam<SearchAuditLog>>)-> Result<impl Responder,UserError>{
+    let vec = AuditService::find_all(state, page).await?;
+    Ok(CommonResult::success(vec))
+}
@@ -1,42 +1,273 @@
-use actix_web::{http, post, HttpRequest, Responder};
-use actix_web::cookie::time::macros::date;
-use actix_web::web::{Data, Json};
+use actix_web::{delete, get, post, put, Either, HttpRequest, HttpResponse, Responder};
+use actix_web::web::{Data, Json, Path, Query};
 use serde::{Deserialize, Serialize};
 use crate::{AppState, UserError};
 use crate::common::result::CommonResult;
-use crate::service::auth::Auth;
+use crate::common::auth_cookie;
+use crate::common::client_ip::client_ip;
+use crate::common::auth_user::AuthUser;
+use crate::common::perm_code;
+use crate::common::rbac::Perm;
+use crate::service::auth::{Auth, SignInResult};
 use crate::service::menu_service::MenuService;
+use crate::service::impersonation_service::ImpersonationService;
+use crate::service::mfa_service::MfaService;
+use crate::service::oidc_service::{OidcCallback, OidcService};
+use crate::service::password_reset_service::PasswordResetService;
+use crate::service::session_service::{ClientInfo, SessionService};
+use crate::service::user_service::{ModifyPassword, UpdateProfile, UserService};
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct UserNamePassword {
     user_name: String,
     password: String,
+    device: Option<String>,
+    /// 使用 cookie 会话，token 不在响应中返回
+    #[serde(default)]
+    cookie: bool,
 }
 #[post("/signin")]
-pub async fn sign_in(state:Data<AppState>, Json(data):Json<UserNamePassword>) ->Result<impl Responder,UserError> {
-    let token = Auth::sign_in(state, data.user_name, data.password).await?;
-    Ok(CommonResult::success(token))
+pub async fn sign_in(state:Data<AppState>, req:HttpRequest, Json(data):Json<UserNamePassword>) ->Result<HttpResponse,UserError> {
+    if data.cookie {
+        auth_cookie::check_enabled()?;
+    }
+    let client = ClientInfo::from_request(&req, data.device);
+    let token = Auth::sign_in(state, data.user_name, data.password, client).await?;
+    match token {
+        SignInResult::Token(token) if data.cookie => auth_cookie::session_response(&req, &token),
+        _ => Ok(CommonResult::success(token).respond_to(&req).map_into_boxed_body()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MfaVerify {
+    mfa_token: String,
+    code: String,
+    device: Option<String>,
+    #[serde(default)]
+    cookie: bool,
+}
+/// cookie 会话时恢复码只能在绑定后通过 `/mfa/confirm` 获取，这里不返回
+#[post("/mfa/verify")]
+pub async fn verify_mfa(state:Data<AppState>, req:HttpRequest, Json(data):Json<MfaVerify>) ->Result<HttpResponse,UserError> {
+    if data.cookie {
+        auth_cookie::check_enabled()?;
+    }
+    let client = ClientInfo::from_request(&req, data.device);
+    let token = Auth::verify_mfa(state, data.mfa_token, data.code, client).await?;
+    if data.cookie {
+        return auth_cookie::session_response(&req, &token.token);
+    }
+    Ok(CommonResult::success(token).respond_to(&req).map_into_boxed_body())
+}
+
+#[post("/mfa/enroll")]
+pub async fn enroll_mfa(state:Data<AppState>, user:AuthUser) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    let enrollment = MfaService::enroll(state, user.id, &user.user_name).await?;
+    Ok(CommonResult::success(enrollment))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MfaCode {
+    code: String,
+}
+#[post("/mfa/confirm")]
+pub async fn confirm_mfa(state:Data<AppState>, user:AuthUser, Json(data):Json<MfaCode>) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    let codes = MfaService::confirm(state, user.id, &data.code).await?;
+    Ok(CommonResult::success(codes))
+}
+
+#[post("/mfa/disable")]
+pub async fn disable_mfa(state:Data<AppState>, user:AuthUser, Json(data):Json<MfaCode>) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    MfaService::disable(state, user.id, &data.code).await?;
+    Ok(CommonResult::<String>::success_none())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExpiredPassword {
+    user_name: String,
+    #[serde(flatten)]
+    password: ModifyPassword,
+}
+#[post("/password/expired")]
+pub async fn change_expired_password(state:Data<AppState>, req:HttpRequest, Json(data):Json<ExpiredPassword>) ->Result<impl Responder,UserError> {
+    let ip = client_ip(&req);
+    Auth::change_expired_password(state, data.user_name, data.password, ip).await?;
+    Ok(CommonResult::<String>::success_none())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ForgotPassword {
+    email: String,
+}
+#[post("/password/forgot")]
+pub async fn forgot_password(state:Data<AppState>, Json(data):Json<ForgotPassword>) ->Result<impl Responder,UserError> {
+    PasswordResetService::forgot(state, data.email).await?;
+    Ok(CommonResult::<String>::success_none())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResetPassword {
+    token: String,
+    new_password: String,
+}
+#[post("/password/reset")]
+pub async fn reset_password(state:Data<AppState>, Json(data):Json<ResetPassword>) ->Result<impl Responder,UserError> {
+    PasswordResetService::reset(state, data.token, data.new_password).await?;
+    Ok(CommonResult::<String>::success_none())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RefreshTokenParam {
+    /// 为空时使用 cookie 中的 refresh token
+    refresh_token: Option<String>,
+}
+#[post("/refresh")]
+pub async fn refresh(state:Data<AppState>, req:HttpRequest, Json(data):Json<RefreshTokenParam>) ->Result<HttpResponse,UserError> {
+    if let Some(refresh_token) = data.refresh_token {
+        let token = Auth::refresh(state, refresh_token).await?;
+        return Ok(CommonResult::success(token).respond_to(&req).map_into_boxed_body());
+    }
+    let Some(refresh_token) = auth_cookie::cookie_value(&req, auth_cookie::REFRESH_COOKIE) else {
+        return Err(UserError::ValidationError { field: "refreshToken".to_string() });
+    };
+    auth_cookie::check_csrf(&req)?;
+    let token = Auth::refresh(state, refresh_token).await?;
+    auth_cookie::session_response(&req, &token)
 }
 
 #[post("/signout")]
-pub async fn sign_out(req:HttpRequest) ->Result<impl Responder,UserError> {
-    let option = req.headers().get(http::header::AUTHORIZATION);
-    if option.is_none() {
-        return Err(UserError::Error("error".to_string()));
+pub async fn sign_out(state:Data<AppState>, req:HttpRequest, user:AuthUser) ->Result<HttpResponse,UserError> {
+    if user.claims.act.is_some() {
+        // 模拟登录的 token 退出时同样记录结束模拟的审计
+        ImpersonationService::stop(state, user, client_ip(&req)).await?;
+    } else {
+        Auth::sign_out(state, user.claims).await?;
     }
-    let result = option.unwrap().to_str();
-    if result.is_err() {
-        return Err(UserError::Error("error".to_string()));
+    let mut response = CommonResult::<String>::success_none().respond_to(&req).map_into_boxed_body();
+    if auth_cookie::cookie_value(&req, auth_cookie::ACCESS_COOKIE).is_some() {
+        auth_cookie::clear_session_cookies(&mut response)?;
     }
-    let token = result.unwrap().to_string();
-    Auth::sign_out(token).await?;
+    Ok(response)
+}
+
+#[get("/me")]
+pub async fn me(state:Data<AppState>, user:AuthUser) ->Result<impl Responder,UserError> {
+    let me = Auth::me(state, user).await?;
+    Ok(CommonResult::success(me))
+}
+
+#[put("/me")]
+pub async fn update_me(state:Data<AppState>, user:AuthUser, Json(profile):Json<UpdateProfile>) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    let model = UserService::update_profile(state, user.id, profile).await?;
+    Ok(CommonResult::success(model))
+}
+
+#[put("/me/password")]
+pub async fn modify_my_psd(state:Data<AppState>, req:HttpRequest, user:AuthUser, Json(pwd):Json<ModifyPassword>) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    let client = ClientInfo::from_request(&req, None);
+    UserService::change_pwd(state, user.id, pwd, client.ip.as_deref()).await?;
+    Ok(CommonResult::<String>::success_none())
+}
+
+#[get("/sessions")]
+pub async fn list_sessions(state:Data<AppState>, user:AuthUser) ->Result<impl Responder,UserError> {
+    let list = SessionService::list_by_user(state, user.id, &user.claims.sid).await?;
+    Ok(CommonResult::success(list))
+}
+
+#[delete("/sessions/{id}")]
+pub async fn revoke_session(state:Data<AppState>, user:AuthUser, id:Path<String>) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    SessionService::revoke(state, user.id, id.into_inner()).await?;
+    Ok(CommonResult::<String>::success_none())
+}
+
+/// 单点登录：返回身份提供方的授权地址
+#[get("/oidc/authorize")]
+pub async fn oidc_authorize(state:Data<AppState>) ->Result<impl Responder,UserError> {
+    let r = OidcService::authorize(state, None).await?;
+    Ok(CommonResult::success(r))
+}
+
+/// 已登录用户绑定外部身份，回调走 `/oidc/link/callback`
+#[post("/oidc/link")]
+pub async fn oidc_link(state:Data<AppState>, user:AuthUser) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    let r = OidcService::authorize(state, Some(user.id)).await?;
+    Ok(CommonResult::success(r))
+}
+
+/// 需要两步验证时返回 `mfaToken`，与密码登录一样再调用 `/auth/mfa/verify`
+#[post("/oidc/callback")]
+pub async fn oidc_callback(state:Data<AppState>, req:HttpRequest, Json(data):Json<OidcCallback>) ->Result<impl Responder,UserError> {
+    let user = OidcService::callback(state.clone(), &data, None).await?;
+    let client = ClientInfo::from_request(&req, data.device);
+    let token = Auth::sign_in_external(state, user, client).await?;
+    Ok(CommonResult::success(token))
+}
+
+/// 绑定外部身份的回调，必须由发起绑定的用户在登录状态下完成，不会创建新的会话
+#[post("/oidc/link/callback")]
+pub async fn oidc_link_callback(state:Data<AppState>, user:AuthUser, Json(data):Json<OidcCallback>) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    OidcService::callback(state, &data, Some(user.id)).await?;
     Ok(CommonResult::<String>::success_none())
 }
 
+#[get("/identities")]
+pub async fn list_identities(state:Data<AppState>, user:AuthUser) ->Result<impl Responder,UserError> {
+    let list = OidcService::find_identities(state, user.id).await?;
+    Ok(CommonResult::success(list))
+}
+
+#[delete("/identities/{id}")]
+pub async fn unlink_identity(state:Data<AppState>, user:AuthUser, id:Path<i32>) ->Result<impl Responder,UserError> {
+    user.forbid_impersonation()?;
+    OidcService::unlink(state, user.id, id.into_inner()).await?;
+    Ok(CommonResult::<String>::success_none())
+}
+
+#[post("/impersonate/stop")]
+pub async fn stop_impersonation(state:Data<AppState>, req:HttpRequest, user:AuthUser) ->Result<impl Responder,UserError> {
+    let ip = client_ip(&req);
+    ImpersonationService::stop(state, user, ip).await?;
+    Ok(CommonResult::<String>::success_none())
+}
+
+/// 以指定用户身份登录，返回短期的 access token（不能刷新）
+#[post("/impersonate/{id}", wrap = "Perm::require(perm_code::ACCOUNT_IMPERSONATE)")]
+pub async fn impersonate(state:Data<AppState>, req:HttpRequest, user:AuthUser, id:Path<i32>) ->Result<impl Responder,UserError> {
+    let ip = client_ip(&req);
+    let r = ImpersonationService::start(state, user, id.into_inner(), ip).await?;
+    Ok(CommonResult::success(r))
+}
+
+#[derive(Debug, Deserialize)]
+struct MenuShape {
+    /// 返回旧版的平铺列表
+    #[serde(default)]
+    flat: bool,
+}
+
+/// 当前用户的菜单树，只包含请求中且用户拥有的权限码
 #[post("/menu")]
-pub async fn get_menu_by_user_auth_code(state:Data<AppState>, Json(data):Json<Vec<String>>) ->Result<impl Responder,UserError> {
-    let vec = MenuService::get_menu_by_user_auth_code(state, data)
-        .await?;
-    Ok(CommonResult::success(vec))
+pub async fn get_menu_by_user_auth_code(state:Data<AppState>, user:AuthUser, Query(shape):Query<MenuShape>, Json(data):Json<Vec<String>>) ->Result<impl Responder,UserError> {
+    let codes = data.into_iter().filter(|c| user.has_perm(c)).collect();
+    if shape.flat {
+        let vec = MenuService::get_menu_by_user_auth_code(state, codes).await?;
+        return Ok(Either::Left(CommonResult::success(vec)));
+    }
+    let tree = MenuService::get_menu_tree_by_user_auth_code(state, codes).await?;
+    Ok(Either::Right(CommonResult::success(tree)))
 }
\ No newline at end of file
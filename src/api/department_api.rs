@@ -1,23 +1,74 @@
-use actix_web::{post, Responder};
-use actix_web::web::{Data, Json};
+use actix_web::{get, post, put, Responder};
+use actix_web::web::{Data, Json, Path};
 use crate::{AppState, UserError};
+use crate::common::perm_code;
+use crate::common::rbac::Perm;
 use crate::common::result::{CommonResult, FilterParam};
-use crate::service::department_service::{CreateDepartment, DepartmentService, SearchParams,DelParams};
+use crate::service::department_service::{CreateDepartment, DepartmentService, SearchParams, DelParams, MoveDepartment, UpdateDepartment};
 
-#[post("/list")]
-pub async fn list(state:Data<AppState>, list:Json<SearchParams>) ->Result<impl Responder,UserError> {
-    let result = DepartmentService::find_all(state, list).await?;
+#[post("/list", wrap = "Perm::require(perm_code::DEPT)")]
+pub async fn list(state:Data<AppState>, page:Json<FilterParam<SearchParams>>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::find_all(state, page).await?;
     Ok(CommonResult::success(result))
 }
 
-#[post("/create")]
+#[post("/create", wrap = "Perm::require(perm_code::DEPT_ADD)")]
 pub async fn create(state:Data<AppState>, create:Json<CreateDepartment>) ->Result<impl Responder,UserError> {
     let result = DepartmentService::create(state, create).await?;
     Ok(CommonResult::success(result))
 }
 
-#[post("/del/")]
+#[post("/del/", wrap = "Perm::require(perm_code::DEPT_DEL)")]
 pub async fn delete(state:Data<AppState>, Json(dels):Json<DelParams>) ->Result<impl Responder,UserError> {
     let result = DepartmentService::delete(state, dels).await?;
     Ok(CommonResult::success(result))
-}
\ No newline at end of file
+}
+
+#[post("/trash", wrap = "Perm::require(perm_code::DEPT_DEL)")]
+pub async fn trash(state:Data<AppState>, Json(page):Json<FilterParam<SearchParams>>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::find_trash(state, page).await?;
+    Ok(CommonResult::success(result))
+}
+
+#[post("/restore", wrap = "Perm::require(perm_code::DEPT_DEL)")]
+pub async fn restore(state:Data<AppState>, Json(ids):Json<DelParams>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::restore(state, ids).await?;
+    Ok(CommonResult::success(result))
+}
+
+#[post("/purge", wrap = "Perm::require(perm_code::DEPT_DEL)")]
+pub async fn purge(state:Data<AppState>, Json(ids):Json<DelParams>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::purge(state, ids).await?;
+    Ok(CommonResult::success(result))
+}
+
+#[get("/tree", wrap = "Perm::require(perm_code::DEPT)")]
+pub async fn tree(state:Data<AppState>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::find_tree(state).await?;
+    Ok(CommonResult::success(result))
+}
+
+#[get("/{id}", wrap = "Perm::require(perm_code::DEPT)")]
+pub async fn find_one(state:Data<AppState>, id:Path<i32>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::find_one(state, id).await?;
+    Ok(CommonResult::success(result))
+}
+
+/// 部门及其全部下级组成的树
+#[get("/{id}/subtree", wrap = "Perm::require(perm_code::DEPT)")]
+pub async fn subtree(state:Data<AppState>, id:Path<i32>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::find_subtree(state, id.into_inner()).await?;
+    Ok(CommonResult::success(result))
+}
+
+#[put("/update", wrap = "Perm::require(perm_code::DEPT_EDIT)")]
+pub async fn update(state:Data<AppState>, update:Json<UpdateDepartment>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::update(state, update).await?;
+    Ok(CommonResult::success(result))
+}
+
+#[post("/{id}/move", wrap = "Perm::require(perm_code::DEPT_EDIT)")]
+pub async fn move_to(state:Data<AppState>, id:Path<i32>, Json(params):Json<MoveDepartment>) ->Result<impl Responder,UserError> {
+    let result = DepartmentService::move_to(state, id.into_inner(), params).await?;
+    Ok(CommonResult::success(result))
+}
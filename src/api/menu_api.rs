@@ -1,42 +1,67 @@
 use actix_web::{get, post, put, Responder};
-use actix_web::web::{Data, Json, Query,Path};
+use actix_web::web::{Data, Json, Path, Query};
 use log::info;
 use crate::{AppState, UserError};
+use crate::common::perm_code;
+use crate::common::rbac::Perm;
 use crate::common::result::{CommonResult, FilterParam};
-use crate::entity::prelude::Menu;
-use crate::service::menu_service::{CreateMenu, DelParams, MenuService, SearchParams, UpdateMenu};
+use crate::service::menu_service::{CreateMenu, DelParams, MenuService, SearchParams, TreeParams, UpdateMenu};
 
-#[post("/create")]
+#[post("/create", wrap = "Perm::require(perm_code::MENU_ADD)")]
 pub async fn create(state: Data<AppState>, Json(create_params) : Json<CreateMenu>) -> Result<impl Responder,UserError> {
     info!("{:?}", create_params);
     let create = MenuService::create(state, create_params).await?;
     Ok(CommonResult::success(create))
 }
 
-#[post("/list")]
+#[post("/list", wrap = "Perm::require(perm_code::MENU)")]
 pub async fn list(state: Data<AppState>, page :Json<FilterParam<SearchParams>>) ->Result<impl Responder,UserError> {
     info!("{:?}", page);
     let all = MenuService::find_all(state, page).await?;
     Ok(CommonResult::success(all))
 }
 
-#[get("/{id}")]
+#[get("/tree", wrap = "Perm::require(perm_code::MENU)")]
+pub async fn tree(state: Data<AppState>, Query(params) :Query<TreeParams>) ->Result<impl Responder,UserError> {
+    let tree = MenuService::find_tree(state, params).await?;
+    Ok(CommonResult::success(tree))
+}
+
+#[get("/{id}", wrap = "Perm::require(perm_code::MENU)")]
 pub async fn find_one(state: Data<AppState>, id :Path<i32>) ->Result<impl Responder,UserError> {
     info!("{:?}", id);
     let one = MenuService::find_one(state,id).await?;
     Ok(CommonResult::success(one))
 }
 
-#[put("/update")]
+#[put("/update", wrap = "Perm::require(perm_code::MENU_EDIT)")]
 pub async fn update(state: Data<AppState>, data :Json<UpdateMenu>) ->Result<impl Responder,UserError> {
     info!("{:?}", data);
     let update = MenuService::update(state, data).await?;
     Ok(CommonResult::success(update))
 }
 
-#[post("/del")]
+#[post("/del", wrap = "Perm::require(perm_code::MENU_DEL)")]
 pub async fn delete(state: Data<AppState>, Json(del):Json<DelParams>) ->Result<impl Responder,UserError> {
     info!("{:?}", del);
     let i = MenuService::delete(state, del).await?;
     Ok(CommonResult::success(i))
-}
\ No newline at end of file
+}
+
+#[post("/trash", wrap = "Perm::require(perm_code::MENU_DEL)")]
+pub async fn trash(state: Data<AppState>, Json(page) :Json<FilterParam<SearchParams>>) ->Result<impl Responder,UserError> {
+    let all = MenuService::find_trash(state, page).await?;
+    Ok(CommonResult::success(all))
+}
+
+#[post("/restore", wrap = "Perm::require(perm_code::MENU_DEL)")]
+pub async fn restore(state: Data<AppState>, Json(ids):Json<DelParams>) ->Result<impl Responder,UserError> {
+    let i = MenuService::restore(state, ids).await?;
+    Ok(CommonResult::success(i))
+}
+
+#[post("/purge", wrap = "Perm::require(perm_code::MENU_DEL)")]
+pub async fn purge(state: Data<AppState>, Json(ids):Json<DelParams>) ->Result<impl Responder,UserError> {
+    let i = MenuService::purge(state, ids).await?;
+    Ok(CommonResult::success(i))
+}
@@ -6,12 +6,21 @@ mod auth_api;
 mod user_api;
 mod role_api;
 mod permission_api;
+mod api_key_api;
+mod oauth_api;
+mod oauth_client_api;
+mod well_known_api;
+mod audit_log_api;
 
 pub fn dispatch(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/menu")
             .service(menu_api::list)
+            .service(menu_api::trash)
+            .service(menu_api::restore)
+            .service(menu_api::purge)
             .service(menu_api::create)
+            .service(menu_api::tree)
             .service(menu_api::find_one)
             .service(menu_api::update)
             .service(menu_api::delete)
@@ -20,7 +29,28 @@ pub fn dispatch(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/auth")
             .service(auth_api::sign_in)
+            .service(auth_api::verify_mfa)
+            .service(auth_api::enroll_mfa)
+            .service(auth_api::confirm_mfa)
+            .service(auth_api::disable_mfa)
+            .service(auth_api::refresh)
+            .service(auth_api::change_expired_password)
+            .service(auth_api::forgot_password)
+            .service(auth_api::reset_password)
             .service(auth_api::sign_out)
+            .service(auth_api::me)
+            .service(auth_api::update_me)
+            .service(auth_api::modify_my_psd)
+            .service(auth_api::list_sessions)
+            .service(auth_api::revoke_session)
+            .service(auth_api::oidc_authorize)
+            .service(auth_api::oidc_link)
+            .service(auth_api::oidc_callback)
+            .service(auth_api::oidc_link_callback)
+            .service(auth_api::list_identities)
+            .service(auth_api::unlink_identity)
+            .service(auth_api::stop_impersonation)
+            .service(auth_api::impersonate)
             .service(auth_api::get_menu_by_user_auth_code)
     );
 
@@ -31,7 +61,11 @@ pub fn dispatch(cfg: &mut web::ServiceConfig) {
             .service(user_api::find_one)
             .service(user_api::create)
             .service(user_api::update)
-            .service(user_api::modify_psd)
+            .service(user_api::unlock)
+            .service(user_api::delete)
+            .service(user_api::trash)
+            .service(user_api::restore)
+            .service(user_api::purge)
     );
 
 
@@ -40,6 +74,14 @@ pub fn dispatch(cfg: &mut web::ServiceConfig) {
             .service(department_api::list)
             .service(department_api::create)
             .service(department_api::delete)
+            .service(department_api::trash)
+            .service(department_api::restore)
+            .service(department_api::purge)
+            .service(department_api::tree)
+            .service(department_api::update)
+            .service(department_api::find_one)
+            .service(department_api::subtree)
+            .service(department_api::move_to)
     );
 
     cfg.service(
@@ -49,6 +91,9 @@ pub fn dispatch(cfg: &mut web::ServiceConfig) {
             .service(role_api::find_one)
             .service(role_api::update)
             .service(role_api::delete)
+            .service(role_api::trash)
+            .service(role_api::restore)
+            .service(role_api::purge)
     );
 
 
@@ -58,4 +103,39 @@ pub fn dispatch(cfg: &mut web::ServiceConfig) {
             .service(permission_api::assign_role_perm_code)
     );
 
+    cfg.service(
+        web::scope("/api-key")
+            .service(api_key_api::list)
+            .service(api_key_api::create)
+            .service(api_key_api::revoke)
+    );
+
+    cfg.service(
+        web::scope("/audit-log")
+            .service(audit_log_api::list)
+    );
+
+    cfg.service(
+        web::scope("/oauth-client")
+            .service(oauth_client_api::list)
+            .service(oauth_client_api::create)
+            .service(oauth_client_api::delete)
+    );
+
+    cfg.service(
+        web::scope("/oauth")
+            .service(oauth_api::authorize)
+            .service(oauth_api::consent)
+            .service(oauth_api::token)
+            .service(oauth_api::introspect)
+            .service(oauth_api::revoke)
+            .service(oauth_api::userinfo)
+    );
+
+    cfg.service(
+        web::scope("/.well-known")
+            .service(well_known_api::jwks)
+            .service(well_known_api::openid_configuration)
+    );
+
 }
\ No newline at end of file
@@ -0,0 +1,51 @@
+use actix_web::{get, post, HttpRequest, HttpResponse, Responder};
+use actix_web::http::header::{AUTHORIZATION, CACHE_CONTROL, LOCATION};
+use actix_web::web::{Data, Form, Json, Query};
+use crate::AppState;
+use crate::common::auth_user::AuthUser;
+use crate::common::result::CommonResult;
+use crate::service::oauth_service::{AuthorizeRequest, OAuthError, OAuthService, TokenParam, TokenRequest};
+
+/// 授权入口，参数校验通过后跳转到前端登录/授权页
+#[get("/authorize")]
+pub async fn authorize(state:Data<AppState>,Query(params):Query<AuthorizeRequest>)->Result<impl Responder,OAuthError> {
+    let location = OAuthService::login_redirect(state, params).await?;
+    Ok(HttpResponse::Found().insert_header((LOCATION, location)).finish())
+}
+
+/// 前端在用户登录并同意后调用，返回带授权码的回调地址
+#[post("/consent")]
+pub async fn consent(state:Data<AppState>,user:AuthUser,Json(params):Json<AuthorizeRequest>)->Result<impl Responder,OAuthError> {
+    // 模拟登录时不能代替用户授权第三方应用
+    user.forbid_impersonation().map_err(|e| OAuthError::access_denied(e.to_string()))?;
+    let r = OAuthService::authorize(state, user.id, params).await?;
+    Ok(CommonResult::success(r))
+}
+
+#[post("/token")]
+pub async fn token(state:Data<AppState>,req:HttpRequest,Form(params):Form<TokenRequest>)->Result<impl Responder,OAuthError> {
+    let r = OAuthService::token(state, &req, params).await?;
+    Ok(HttpResponse::Ok().insert_header((CACHE_CONTROL, "no-store")).json(r))
+}
+
+#[post("/introspect")]
+pub async fn introspect(state:Data<AppState>,req:HttpRequest,Form(params):Form<TokenParam>)->Result<impl Responder,OAuthError> {
+    let r = OAuthService::introspect(state, &req, params).await?;
+    Ok(HttpResponse::Ok().json(r))
+}
+
+#[post("/revoke")]
+pub async fn revoke(state:Data<AppState>,req:HttpRequest,Form(params):Form<TokenParam>)->Result<impl Responder,OAuthError> {
+    OAuthService::revoke(state, &req, params).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[get("/userinfo")]
+pub async fn userinfo(state:Data<AppState>,req:HttpRequest)->Result<impl Responder,OAuthError> {
+    let bearer = req.headers().get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| OAuthError::invalid_token("bearer token is required"))?;
+    let r = OAuthService::userinfo(state, bearer.trim()).await?;
+    Ok(HttpResponse::Ok().json(r))
+}
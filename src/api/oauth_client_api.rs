@@ -0,0 +1,25 @@
+use actix_web::{delete, post, Responder};
+use actix_web::web::{Data, Json, Path};
+use crate::{AppState, UserError};
+use crate::common::perm_code;
+use crate::common::rbac::Perm;
+use crate::common::result::{CommonResult, FilterParam};
+use crate::service::oauth_client_service::{CreateOAuthClient, OAuthClientService, SearchOAuthClient};
+
+#[post("/list", wrap = "Perm::require(perm_code::OAUTH_CLIENT)")]
+pub async fn list(state:Data<AppState>,Json(page): Json<FilterParam<SearchOAuthClient>>)-> Result<impl Responder,UserError>{
+    let vec = OAuthClientService::find_all(state, page).await?;
+    Ok(CommonResult::success(vec))
+}
+
+#[post("/create", wrap = "Perm::require(perm_code::OAUTH_CLIENT_ADD)")]
+pub async fn create(state:Data<AppState>,Json(dto):Json<CreateOAuthClient>)->Result<impl Responder,UserError> {
+    let r = OAuthClientService::create(state, dto).await?;
+    Ok(CommonResult::success(r))
+}
+
+#[delete("/{id}", wrap = "Perm::require(perm_code::OAUTH_CLIENT_DEL)")]
+pub async fn delete(state:Data<AppState>,id:Path<i32>)->Result<impl Responder,UserError> {
+    OAuthClientService::delete(state, id.into_inner()).await?;
+    Ok(CommonResult::<String>::success_none())
+}
@@ -1,35 +1,55 @@
 use actix_web::{get, post, put, Responder};
 use actix_web::web::{Data, Json, Path};
 use crate::{AppState, UserError};
+use crate::common::perm_code;
+use crate::common::rbac::Perm;
 use crate::common::result::{CommonResult, FilterParam};
 use crate::service::role_service::{CreateRoleDto, DelParams, RoleService, SearchRoleDto, UpdateRole};
 
-#[post("/list")]
+#[post("/list", wrap = "Perm::require(perm_code::ROLE)")]
 pub async fn list(state:Data<AppState>,Json(page): Json<FilterParam<SearchRoleDto>>)-> Result<impl Responder,UserError>{
     let vec = RoleService::find_all(state, page).await?;
     Ok(CommonResult::success(vec))
 }
 
-#[post("/create")]
+#[post("/create", wrap = "Perm::require(perm_code::ROLE_ADD)")]
 pub async fn create(state:Data<AppState>,Json(create): Json<CreateRoleDto>)-> Result<impl Responder,UserError>{
     let vec = RoleService::create(state, create).await?;
     Ok(CommonResult::success(vec))
 }
 
-#[get("/{id}")]
+#[get("/{id}", wrap = "Perm::require(perm_code::ROLE)")]
 pub async fn find_one(state:Data<AppState>,id: Path<i32>)-> Result<impl Responder,UserError>{
     let data = RoleService::find_one(state, id.into_inner()).await?;
     Ok(CommonResult::success(data))
 }
 
-#[put("/update")]
+#[put("/update", wrap = "Perm::require(perm_code::ROLE_EDIT)")]
 pub async fn update(state:Data<AppState>,Json(update): Json<UpdateRole>)-> Result<impl Responder,UserError>{
     let data = RoleService::update(state, update).await?;
     Ok(CommonResult::success(data))
 }
 
-#[post("/del")]
+#[post("/del", wrap = "Perm::require(perm_code::ROLE_DEL)")]
 pub async fn delete(state:Data<AppState>,Json(dels): Json<DelParams>)-> Result<impl Responder,UserError>{
     let data = RoleService::delete(state, dels).await?;
     Ok(CommonResult::success(data))
-}
\ No newline at end of file
+}
+
+#[post("/trash", wrap = "Perm::require(perm_code::ROLE_DEL)")]
+pub async fn trash(state:Data<AppState>,Json(page): Json<FilterParam<SearchRoleDto>>)-> Result<impl Responder,UserError>{
+    let vec = RoleService::find_trash(state, page).await?;
+    Ok(CommonResult::success(vec))
+}
+
+#[post("/restore", wrap = "Perm::require(perm_code::ROLE_DEL)")]
+pub async fn restore(state:Data<AppState>,Json(ids): Json<DelParams>)-> Result<impl Responder,UserError>{
+    let data = RoleService::restore(state, ids).await?;
+    Ok(CommonResult::success(data))
+}
+
+#[post("/purge", wrap = "Perm::require(perm_code::ROLE_DEL)")]
+pub async fn purge(state:Data<AppState>,Json(ids): Json<DelParams>)-> Result<impl Responder,UserError>{
+    let data = RoleService::purge(state, ids).await?;
+    Ok(CommonResult::success(data))
+}
@@ -1,41 +1,67 @@
 use actix_web::{get, post, put, Responder};
 use actix_web::web::{Data, Json, Path};
 use crate::{AppState, UserError};
+use crate::common::perm_code;
+use crate::common::rbac::Perm;
 use crate::common::result::{CommonResult, FilterParam};
-use crate::service::user_service::{ChangePassword, CreateUser, SearchParams, UpdateUser, UserService};
+use crate::service::user_service::{CreateUser, DeleteParam, SearchParams, UpdateUser, UserService};
 
-#[get("/auth-code/{id}")]
+#[get("/auth-code/{id}", wrap = "Perm::require(perm_code::ACCOUNT)")]
 pub async fn find_one_auth_code(state:Data<AppState>,path:Path<i32>)-> Result<impl Responder,UserError>{
     let vec = UserService::find_one_auth_code(state, path.into_inner()).await?;
     Ok(CommonResult::success(vec))
 }
 
-#[post("/list")]
+#[post("/list", wrap = "Perm::require(perm_code::ACCOUNT)")]
 pub async fn list(state:Data<AppState>,Json(page): Json<FilterParam<SearchParams>>)-> Result<impl Responder,UserError>{
     let vec = UserService::find_all(state, page).await?;
     Ok(CommonResult::success(vec))
 }
 
-#[get("/{id}")]
+#[get("/{id}", wrap = "Perm::require(perm_code::ACCOUNT)")]
 pub async fn find_one(state:Data<AppState>,id: Path<i32>)-> Result<impl Responder,UserError>{
     let vec = UserService::find_one(state, id.into_inner()).await?;
     Ok(CommonResult::success(vec))
 }
 
-#[post("/create")]
+#[post("/create", wrap = "Perm::require(perm_code::ACCOUNT_ADD)")]
 pub async fn create(state:Data<AppState>,Json(user):Json<CreateUser>)->Result<impl Responder,UserError> {
     let r = UserService::create_user(state, user).await?;
     Ok(CommonResult::success(r))
 }
 
-#[put("/update")]
+#[put("/update", wrap = "Perm::require(perm_code::ACCOUNT_EDIT)")]
 pub async fn update(state:Data<AppState>,Json(user):Json<UpdateUser>)->Result<impl Responder,UserError> {
     let r = UserService::update(state, user).await?;
     Ok(CommonResult::success(r))
 }
 
-#[put("/psd")]
-pub async fn modify_psd(state:Data<AppState>,Json(pwd):Json<ChangePassword>)->Result<impl Responder,UserError> {
-    let _ = UserService::change_pwd(state, pwd).await?;
+#[post("/unlock/{id}", wrap = "Perm::require(perm_code::ACCOUNT_EDIT)")]
+pub async fn unlock(state:Data<AppState>,id:Path<i32>)->Result<impl Responder,UserError> {
+    UserService::unlock(state, id.into_inner()).await?;
     Ok(CommonResult::<String>::success_none())
-}
\ No newline at end of file
+}
+
+#[post("/del", wrap = "Perm::require(perm_code::ACCOUNT_DEL)")]
+pub async fn delete(state:Data<AppState>,Json(ids):Json<DeleteParam>)->Result<impl Responder,UserError> {
+    let r = UserService::delete(state, ids).await?;
+    Ok(CommonResult::success(r))
+}
+
+#[post("/trash", wrap = "Perm::require(perm_code::ACCOUNT_DEL)")]
+pub async fn trash(state:Data<AppState>,Json(page): Json<FilterParam<SearchParams>>)-> Result<impl Responder,UserError>{
+    let vec = UserService::find_trash(state, page).await?;
+    Ok(CommonResult::success(vec))
+}
+
+#[post("/restore", wrap = "Perm::require(perm_code::ACCOUNT_DEL)")]
+pub async fn restore(state:Data<AppState>,Json(ids):Json<DeleteParam>)->Result<impl Responder,UserError> {
+    let r = UserService::restore(state, ids).await?;
+    Ok(CommonResult::success(r))
+}
+
+#[post("/purge", wrap = "Perm::require(perm_code::ACCOUNT_DEL)")]
+pub async fn purge(state:Data<AppState>,Json(ids):Json<DeleteParam>)->Result<impl Responder,UserError> {
+    let r = UserService::purge(state, ids).await?;
+    Ok(CommonResult::success(r))
+}
@@ -0,0 +1,15 @@
+use actix_web::{get, HttpResponse, Responder};
+use crate::common::jwt_keys::JWT_KEYS;
+use crate::service::oauth_service::OAuthService;
+
+/// 公开验证 token 所需的公钥，HS256 时为空
+#[get("/jwks.json")]
+pub async fn jwks() -> impl Responder {
+    HttpResponse::Ok().json(&JWT_KEYS.jwks)
+}
+
+/// OIDC discovery 文档
+#[get("/openid-configuration")]
+pub async fn openid_configuration() -> impl Responder {
+    HttpResponse::Ok().json(OAuthService::discovery())
+}
@@ -0,0 +1,142 @@
+//! cookie 会话
+//!
+//! 登录时选择 cookie 模式，access token 和 refresh token 放在 HttpOnly cookie 中，
+//! 同时下发 JS 可读的 `XSRF-TOKEN` cookie。使用 cookie 认证的非安全方法请求必须在
+//! `X-XSRF-TOKEN` 头中带上相同的值（double-submit），Angular 的 HttpClient 默认会这样做。
+
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::cookie::time::Duration;
+use actix_web::http::Method;
+use crate::UserError;
+use crate::common::config::{AUTH_COOKIE_DOMAIN, AUTH_COOKIE_ENABLED, AUTH_COOKIE_SAME_SITE, AUTH_COOKIE_SECURE, REFRESH_TOKEN_TTL_DAYS};
+use crate::common::result::CommonResult;
+use crate::common::security::{Security, ACCESS_TOKEN_EXPIRES_SECS};
+use crate::service::auth::TokenPair;
+
+pub const ACCESS_COOKIE: &str = "access_token";
+pub const REFRESH_COOKIE: &str = "refresh_token";
+pub const CSRF_COOKIE: &str = "XSRF-TOKEN";
+pub const CSRF_HEADER: &str = "X-XSRF-TOKEN";
+/// refresh token 只发送给刷新接口
+const REFRESH_COOKIE_PATH: &str = "/auth/refresh";
+
+/// cookie 模式登录的返回，token 只在 cookie 中
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieSession {
+    pub expires_in: i64,
+    pub csrf_token: String,
+}
+
+pub fn check_enabled() -> Result<(), UserError> {
+    if !*AUTH_COOKIE_ENABLED {
+        return Err(UserError::Error("cookie session is not enabled".to_string()));
+    }
+    Ok(())
+}
+
+fn same_site() -> SameSite {
+    match AUTH_COOKIE_SAME_SITE.to_lowercase().as_str() {
+        "strict" => SameSite::Strict,
+        "none" => SameSite::None,
+        _ => SameSite::Lax,
+    }
+}
+
+fn build_cookie(name: &'static str, value: String, path: &'static str, http_only: bool, max_age: Duration) -> Cookie<'static> {
+    let mut cookie = Cookie::build(name, value)
+        .path(path)
+        .http_only(http_only)
+        .secure(*AUTH_COOKIE_SECURE || same_site() == SameSite::None)
+        .same_site(same_site())
+        .max_age(max_age)
+        .finish();
+    if !AUTH_COOKIE_DOMAIN.is_empty() {
+        cookie.set_domain(AUTH_COOKIE_DOMAIN.clone());
+    }
+    cookie
+}
+
+/// 把 token 写入 cookie 并生成新的 CSRF token，响应中不包含 token
+pub fn session_response(req: &HttpRequest, token: &TokenPair) -> Result<HttpResponse, UserError> {
+    let csrf_token = Security::generate_opaque_token();
+    let refresh_age = Duration::days(*REFRESH_TOKEN_TTL_DAYS);
+    let cookies = [
+        build_cookie(ACCESS_COOKIE, token.access_token.clone(), "/", true, Duration::seconds(ACCESS_TOKEN_EXPIRES_SECS)),
+        build_cookie(REFRESH_COOKIE, token.refresh_token.clone(), REFRESH_COOKIE_PATH, true, refresh_age),
+        build_cookie(CSRF_COOKIE, csrf_token.clone(), "/", false, refresh_age),
+    ];
+    let mut response = CommonResult::success(CookieSession { expires_in: token.expires_in, csrf_token }).respond_to(req).map_into_boxed_body();
+    for cookie in cookies {
+        response.add_cookie(&cookie).map_err(|e| UserError::Error(e.to_string()))?;
+    }
+    Ok(response)
+}
+
+pub fn clear_session_cookies(response: &mut HttpResponse) -> Result<(), UserError> {
+    for (name, path, http_only) in [
+        (ACCESS_COOKIE, "/", true),
+        (REFRESH_COOKIE, REFRESH_COOKIE_PATH, true),
+        (CSRF_COOKIE, "/", false),
+    ] {
+        let cookie = build_cookie(name, String::new(), path, http_only, Duration::ZERO);
+        response.add_cookie(&cookie).map_err(|e| UserError::Error(e.to_string()))?;
+    }
+    Ok(())
+}
+
+pub fn cookie_value(req: &HttpRequest, name: &str) -> Option<String> {
+    if !*AUTH_COOKIE_ENABLED {
+        return None;
+    }
+    req.cookie(name).map(|c| c.value().to_string()).filter(|v| !v.is_empty())
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// 比较时间与内容无关，避免逐字节猜测
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// double-submit 校验：非安全方法的请求头必须与 CSRF cookie 一致
+pub fn check_csrf(req: &HttpRequest) -> Result<(), UserError> {
+    if is_safe_method(req.method()) {
+        return Ok(());
+    }
+    let header = req.headers().get(CSRF_HEADER).and_then(|v| v.to_str().ok());
+    let cookie = req.cookie(CSRF_COOKIE);
+    match (header, cookie) {
+        (Some(header), Some(cookie)) if !header.is_empty() && constant_time_eq(header.as_bytes(), cookie.value().as_bytes()) => Ok(()),
+        _ => Err(UserError::Forbidden("invalid csrf token".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::cookie::Cookie;
+    use actix_web::test::TestRequest;
+    use crate::common::auth_cookie::{check_csrf, constant_time_eq, CSRF_COOKIE, CSRF_HEADER};
+
+    #[test]
+    fn test_check_csrf() {
+        let req = TestRequest::get().to_http_request();
+        assert!(check_csrf(&req).is_ok());
+        let req = TestRequest::post().cookie(Cookie::new(CSRF_COOKIE, "abc")).to_http_request();
+        assert!(check_csrf(&req).is_err());
+        let req = TestRequest::post()
+            .cookie(Cookie::new(CSRF_COOKIE, "abc"))
+            .insert_header((CSRF_HEADER, "abd"))
+            .to_http_request();
+        assert!(check_csrf(&req).is_err());
+        let req = TestRequest::delete()
+            .cookie(Cookie::new(CSRF_COOKIE, "abc"))
+            .insert_header((CSRF_HEADER, "abc"))
+            .to_http_request();
+        assert!(check_csrf(&req).is_ok());
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+}
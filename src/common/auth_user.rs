@@ -0,0 +1,79 @@
+use actix_web::dev::Payload;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use futures::future::LocalBoxFuture;
+use crate::{AppState, UserError};
+use crate::common::security::Claims;
+use crate::service::user_service::UserService;
+
+/// 当前登录用户，由 `validator` 放入的 `Claims` 构造。
+/// token 中的角色和权限码在签发后可能已被修改，以数据库为准（带短时缓存）；
+/// API key 的 claims 每次请求现查并按 scopes 裁剪过，直接使用。
+///
+/// ```ignore
+/// pub async fn handler(user: AuthUser) -> Result<impl Responder, UserError>
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: i32,
+    pub user_name: String,
+    pub roles: Vec<i32>,
+    pub perms: Vec<String>,
+    pub claims: Claims,
+}
+
+impl AuthUser {
+    pub async fn load(state: Data<AppState>, claims: Claims) -> Result<AuthUser, UserError> {
+        let id = claims.user_id()?;
+        let (roles, perms) = match &claims.perms {
+            Some(perms) if claims.is_api_key() => (claims.roles.clone(), perms.clone()),
+            _ => UserService::find_grants(state, id).await?,
+        };
+        Ok(AuthUser {
+            id,
+            user_name: claims.user_name.clone(),
+            roles,
+            perms,
+            claims,
+        })
+    }
+
+    pub fn has_perm(&self, code: &str) -> bool {
+        self.perms.iter().any(|c| c == code)
+    }
+
+    /// 模拟登录时不允许修改账号的安全设置（密码、两步验证、会话）
+    pub fn forbid_impersonation(&self) -> Result<(), UserError> {
+        if self.claims.act.is_some() {
+            return Err(UserError::Forbidden("not allowed while impersonating".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 从请求中取出当前用户，同一请求内只构造一次
+    pub async fn from_req(req: &HttpRequest) -> Result<AuthUser, UserError> {
+        if let Some(user) = req.extensions().get::<AuthUser>() {
+            return Ok(user.clone());
+        }
+        let claims = req.extensions().get::<Claims>().cloned();
+        let Some(claims) = claims else {
+            return Err(UserError::Unauthorized("Unauthorized".to_string()));
+        };
+        let Some(state) = req.app_data::<Data<AppState>>().cloned() else {
+            return Err(UserError::Error("app state is missing".to_string()));
+        };
+        let user = AuthUser::load(state, claims).await?;
+        req.extensions_mut().insert(user.clone());
+        Ok(user)
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move { AuthUser::from_req(&req).await.map_err(Into::into) })
+    }
+}
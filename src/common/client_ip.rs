@@ -0,0 +1,97 @@
+//! 客户端 IP
+//!
+//! 默认只使用 TCP 连接的对端地址。部署在反向代理之后时，把代理地址加入 `TRUSTED_PROXIES`，
+//! 只有来自这些地址的请求才读取 `X-Forwarded-For`，并从右向左取第一个不是可信代理的地址，
+//! 客户端自己伪造的头部在最左侧，不会被采用。
+
+use std::net::IpAddr;
+use actix_web::HttpRequest;
+use once_cell::sync::Lazy;
+use crate::common::config::TRUSTED_PROXIES;
+
+const X_FORWARDED_FOR: &str = "X-Forwarded-For";
+
+/// 启动时解析，配置有误直接退出
+pub static TRUSTED_PROXY_NETS: Lazy<Vec<(IpAddr, u32)>> = Lazy::new(|| {
+    TRUSTED_PROXIES.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|item| parse_net(item).unwrap_or_else(|| panic!("invalid TRUSTED_PROXIES item {}", item)))
+        .collect()
+});
+
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    let peer = req.peer_addr()?.ip();
+    let forwarded = req.headers().get(X_FORWARDED_FOR).and_then(|v| v.to_str().ok());
+    Some(resolve(peer, forwarded, &TRUSTED_PROXY_NETS).to_string())
+}
+
+fn resolve(peer: IpAddr, forwarded: Option<&str>, trusted: &[(IpAddr, u32)]) -> IpAddr {
+    let mut ip = peer;
+    if !contains(trusted, ip) {
+        return ip;
+    }
+    let Some(forwarded) = forwarded else {
+        return ip;
+    };
+    for item in forwarded.rsplit(',') {
+        let Ok(addr) = item.trim().parse::<IpAddr>() else {
+            return ip;
+        };
+        ip = addr;
+        if !contains(trusted, ip) {
+            break;
+        }
+    }
+    ip
+}
+
+/// 解析 IP 或 CIDR，返回网络地址和前缀长度
+pub fn parse_net(item: &str) -> Option<(IpAddr, u32)> {
+    let (addr, bits) = match item.split_once('/') {
+        Some((addr, bits)) => (addr.parse::<IpAddr>().ok()?, Some(bits.parse::<u32>().ok()?)),
+        None => (item.parse::<IpAddr>().ok()?, None),
+    };
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    match bits {
+        Some(bits) if bits > max => None,
+        Some(bits) => Some((addr, bits)),
+        None => Some((addr, max)),
+    }
+}
+
+/// `ip` 是否属于其中某个网络
+pub fn contains(nets: &[(IpAddr, u32)], ip: IpAddr) -> bool {
+    nets.iter().any(|(net, bits)| match (*net, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) => {
+            let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+            u32::from(net) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) => {
+            let mask = u128::MAX.checked_shl(128 - bits).unwrap_or(0);
+            u128::from(net) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let trusted = vec![parse_net("10.0.0.0/8").unwrap()];
+        // 不是可信代理，忽略头部
+        assert_eq!(ip("1.2.3.4"), resolve(ip("1.2.3.4"), Some("9.9.9.9"), &trusted));
+        assert_eq!(ip("1.2.3.4"), resolve(ip("1.2.3.4"), Some("9.9.9.9"), &[]));
+        // 最左侧是客户端伪造的
+        assert_eq!(ip("5.6.7.8"), resolve(ip("10.0.0.1"), Some("9.9.9.9, 5.6.7.8, 10.0.0.2"), &trusted));
+        assert_eq!(ip("10.0.0.1"), resolve(ip("10.0.0.1"), None, &trusted));
+        assert_eq!(ip("10.0.0.2"), resolve(ip("10.0.0.1"), Some("garbage, 10.0.0.2"), &trusted));
+    }
+}
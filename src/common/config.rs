@@ -0,0 +1,188 @@
+use std::env;
+use std::str::FromStr;
+use once_cell::sync::Lazy;
+
+/// 读取环境变量，未设置或解析失败时使用默认值
+pub fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key).ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+/// refresh token 有效期（天）
+pub static REFRESH_TOKEN_TTL_DAYS: Lazy<i64> = Lazy::new(|| env_or("REFRESH_TOKEN_TTL_DAYS", 14));
+
+/// 清理已过期吊销记录的间隔（秒）
+pub static REVOKED_TOKEN_PURGE_INTERVAL_SECS: Lazy<u64> = Lazy::new(|| env_or("REVOKED_TOKEN_PURGE_INTERVAL_SECS", 3600));
+
+/// access token 中权限码的最大字节数，超过则不放入 token
+pub static JWT_PERMS_MAX_BYTES: Lazy<usize> = Lazy::new(|| env_or("JWT_PERMS_MAX_BYTES", 2048));
+
+/// 登录失败计数存储：memory（单节点）或 database（集群）
+pub static LOGIN_ATTEMPT_STORE: Lazy<String> = Lazy::new(|| env_or("LOGIN_ATTEMPT_STORE", "memory".to_string()));
+
+/// 同一用户名连续失败多少次后锁定
+pub static LOGIN_MAX_FAILURES_PER_USER: Lazy<i32> = Lazy::new(|| env_or("LOGIN_MAX_FAILURES_PER_USER", 5));
+
+/// 同一 IP 连续失败多少次后限流
+pub static LOGIN_MAX_FAILURES_PER_IP: Lazy<i32> = Lazy::new(|| env_or("LOGIN_MAX_FAILURES_PER_IP", 20));
+
+/// 首次锁定时长（秒），之后每多失败一次翻倍
+pub static LOGIN_LOCK_SECS: Lazy<i64> = Lazy::new(|| env_or("LOGIN_LOCK_SECS", 60));
+
+/// 最长锁定时长（秒）
+pub static LOGIN_LOCK_MAX_SECS: Lazy<i64> = Lazy::new(|| env_or("LOGIN_LOCK_MAX_SECS", 3600));
+
+/// 失败计数的统计窗口（秒），超过窗口未再失败则重新计数
+pub static LOGIN_FAILURE_WINDOW_SECS: Lazy<i64> = Lazy::new(|| env_or("LOGIN_FAILURE_WINDOW_SECS", 900));
+
+/// 用户启用状态的缓存时间（秒），禁用用户后最长在该时间内失效
+pub static USER_STATUS_CACHE_SECS: Lazy<u64> = Lazy::new(|| env_or("USER_STATUS_CACHE_SECS", 30));
+
+/// 用户角色和权限码的缓存时间（秒），本节点修改后立即生效，其他节点最长在该时间内生效
+pub static PERM_CACHE_SECS: Lazy<u64> = Lazy::new(|| env_or("PERM_CACHE_SECS", 30));
+
+/// 禁止重复使用最近几次的密码
+pub static PASSWORD_HISTORY_SIZE: Lazy<u64> = Lazy::new(|| env_or("PASSWORD_HISTORY_SIZE", 5));
+
+/// 密码最长使用天数，超过后登录时要求修改，0 表示不限制
+pub static PASSWORD_MAX_AGE_DAYS: Lazy<i64> = Lazy::new(|| env_or("PASSWORD_MAX_AGE_DAYS", 0));
+
+/// Argon2id 内存开销（KiB），默认与 `argon2::Params::DEFAULT_M_COST` 一致
+pub static ARGON2_MEMORY_KIB: Lazy<u32> = Lazy::new(|| env_or("ARGON2_MEMORY_KIB", 19 * 1024));
+
+/// Argon2id 迭代次数
+pub static ARGON2_ITERATIONS: Lazy<u32> = Lazy::new(|| env_or("ARGON2_ITERATIONS", 2));
+
+/// Argon2id 并行度
+pub static ARGON2_PARALLELISM: Lazy<u32> = Lazy::new(|| env_or("ARGON2_PARALLELISM", 1));
+
+/// 找回密码链接有效期（分钟）
+pub static PASSWORD_RESET_TOKEN_TTL_MINUTES: Lazy<i64> = Lazy::new(|| env_or("PASSWORD_RESET_TOKEN_TTL_MINUTES", 30));
+
+/// 重置密码页面地址，token 拼接在末尾
+pub static PASSWORD_RESET_URL: Lazy<String> = Lazy::new(|| env_or("PASSWORD_RESET_URL", "http://localhost:4200/login/reset-password?token=".to_string()));
+
+/// 邮件发送方式：smtp、file（写入 `MAIL_OUTBOX_DIR`）或 log（只打印日志）
+pub static MAIL_SENDER: Lazy<String> = Lazy::new(|| env_or("MAIL_SENDER", "log".to_string()));
+
+/// 发件人地址
+pub static MAIL_FROM: Lazy<String> = Lazy::new(|| env_or("MAIL_FROM", "no-reply@localhost".to_string()));
+
+/// file 方式下邮件的保存目录
+pub static MAIL_OUTBOX_DIR: Lazy<String> = Lazy::new(|| env_or("MAIL_OUTBOX_DIR", "outbox".to_string()));
+
+pub static SMTP_HOST: Lazy<String> = Lazy::new(|| env_or("SMTP_HOST", "localhost".to_string()));
+
+pub static SMTP_PORT: Lazy<u16> = Lazy::new(|| env_or("SMTP_PORT", 587));
+
+pub static SMTP_USERNAME: Lazy<String> = Lazy::new(|| env_or("SMTP_USERNAME", String::new()));
+
+pub static SMTP_PASSWORD: Lazy<String> = Lazy::new(|| env_or("SMTP_PASSWORD", String::new()));
+
+/// 两步验证应用中显示的发行方名称
+pub static MFA_ISSUER: Lazy<String> = Lazy::new(|| env_or("MFA_ISSUER", "ng-antd-admin".to_string()));
+
+/// 登录第一步返回的待验证 token 有效期（秒）
+pub static MFA_TOKEN_EXPIRES_SECS: Lazy<i64> = Lazy::new(|| env_or("MFA_TOKEN_EXPIRES_SECS", 300));
+
+/// OAuth2 / OIDC 签发方，即本服务对外的地址
+pub static OAUTH_ISSUER: Lazy<String> = Lazy::new(|| env_or("OAUTH_ISSUER", "http://localhost:3000".to_string()));
+
+/// 前端的登录授权页面，`/oauth/authorize` 带上原始参数跳转到这里
+pub static OAUTH_LOGIN_URL: Lazy<String> = Lazy::new(|| env_or("OAUTH_LOGIN_URL", "http://localhost:4200/oauth/authorize".to_string()));
+
+/// OAuth2 access token 有效期（秒）
+pub static OAUTH_ACCESS_TOKEN_EXPIRES_SECS: Lazy<i64> = Lazy::new(|| env_or("OAUTH_ACCESS_TOKEN_EXPIRES_SECS", 3600));
+
+/// 授权码有效期（秒）
+pub static OAUTH_CODE_EXPIRES_SECS: Lazy<i64> = Lazy::new(|| env_or("OAUTH_CODE_EXPIRES_SECS", 60));
+
+/// 外部 OIDC 身份提供方的 issuer，为空时不启用单点登录
+pub static OIDC_ISSUER: Lazy<String> = Lazy::new(|| env_or("OIDC_ISSUER", String::new()));
+
+/// 保存在身份关联表中的提供方名称
+pub static OIDC_PROVIDER: Lazy<String> = Lazy::new(|| env_or("OIDC_PROVIDER", "sso".to_string()));
+
+pub static OIDC_CLIENT_ID: Lazy<String> = Lazy::new(|| env_or("OIDC_CLIENT_ID", String::new()));
+
+/// 为空时按公开客户端处理，只使用 PKCE
+pub static OIDC_CLIENT_SECRET: Lazy<String> = Lazy::new(|| env_or("OIDC_CLIENT_SECRET", String::new()));
+
+/// 前端的回调页面，收到 code 和 state 后调用 `/auth/oidc/callback`
+pub static OIDC_REDIRECT_URI: Lazy<String> = Lazy::new(|| env_or("OIDC_REDIRECT_URI", "http://localhost:4200/login/oidc-callback".to_string()));
+
+pub static OIDC_SCOPES: Lazy<String> = Lazy::new(|| env_or("OIDC_SCOPES", "openid profile email".to_string()));
+
+/// 外部身份没有关联本地用户时自动创建用户
+pub static OIDC_JIT_PROVISIONING: Lazy<bool> = Lazy::new(|| env_or("OIDC_JIT_PROVISIONING", false));
+
+/// 自动创建的用户所属部门
+pub static OIDC_DEFAULT_DEPARTMENT_ID: Lazy<i32> = Lazy::new(|| env_or("OIDC_DEFAULT_DEPARTMENT_ID", 1));
+
+/// 自动创建的用户的角色，0 表示不分配角色
+pub static OIDC_DEFAULT_ROLE_ID: Lazy<i32> = Lazy::new(|| env_or("OIDC_DEFAULT_ROLE_ID", 0));
+
+/// 跳转到身份提供方后完成登录的时限（秒）
+pub static OIDC_LOGIN_STATE_EXPIRES_SECS: Lazy<i64> = Lazy::new(|| env_or("OIDC_LOGIN_STATE_EXPIRES_SECS", 600));
+
+/// 没有指定认证方式的用户按顺序尝试的认证方式，逗号分隔，可选 local、ldap
+pub static AUTH_PROVIDER_CHAIN: Lazy<String> = Lazy::new(|| env_or("AUTH_PROVIDER_CHAIN", "local".to_string()));
+
+/// LDAP 服务器地址，如 `ldap://localhost:389`，为空时不启用 LDAP
+pub static LDAP_URL: Lazy<String> = Lazy::new(|| env_or("LDAP_URL", String::new()));
+
+/// 查找用户时使用的账号，为空时匿名查找
+pub static LDAP_BIND_DN: Lazy<String> = Lazy::new(|| env_or("LDAP_BIND_DN", String::new()));
+
+pub static LDAP_BIND_PASSWORD: Lazy<String> = Lazy::new(|| env_or("LDAP_BIND_PASSWORD", String::new()));
+
+pub static LDAP_BASE_DN: Lazy<String> = Lazy::new(|| env_or("LDAP_BASE_DN", "dc=example,dc=org".to_string()));
+
+/// 查找用户的过滤器，`{username}` 替换为转义后的登录名
+pub static LDAP_USER_FILTER: Lazy<String> = Lazy::new(|| env_or("LDAP_USER_FILTER", "(uid={username})".to_string()));
+
+pub static LDAP_EMAIL_ATTRIBUTE: Lazy<String> = Lazy::new(|| env_or("LDAP_EMAIL_ATTRIBUTE", "mail".to_string()));
+
+/// 用户所属组的属性，值为组的 DN
+pub static LDAP_GROUP_ATTRIBUTE: Lazy<String> = Lazy::new(|| env_or("LDAP_GROUP_ATTRIBUTE", "memberOf".to_string()));
+
+/// 组和角色的映射，`组DN:角色id`，分号分隔
+pub static LDAP_GROUP_ROLE_MAP: Lazy<String> = Lazy::new(|| env_or("LDAP_GROUP_ROLE_MAP", String::new()));
+
+/// 首次登录时创建的用户所属部门
+pub static LDAP_DEFAULT_DEPARTMENT_ID: Lazy<i32> = Lazy::new(|| env_or("LDAP_DEFAULT_DEPARTMENT_ID", 1));
+
+pub static LDAP_STARTTLS: Lazy<bool> = Lazy::new(|| env_or("LDAP_STARTTLS", false));
+
+pub static LDAP_TIMEOUT_SECS: Lazy<u64> = Lazy::new(|| env_or("LDAP_TIMEOUT_SECS", 5));
+
+/// 模拟登录 token 的有效期（秒），到期后需要重新发起
+pub static IMPERSONATION_EXPIRES_SECS: Lazy<i64> = Lazy::new(|| env_or("IMPERSONATION_EXPIRES_SECS", 900));
+
+/// 允许登录时选择 cookie 会话（服务端渲染页面无法携带 Bearer 头）
+pub static AUTH_COOKIE_ENABLED: Lazy<bool> = Lazy::new(|| env_or("AUTH_COOKIE_ENABLED", false));
+
+/// 只在 HTTPS 下发送 cookie，本地 HTTP 调试时可关闭
+pub static AUTH_COOKIE_SECURE: Lazy<bool> = Lazy::new(|| env_or("AUTH_COOKIE_SECURE", true));
+
+/// Strict、Lax 或 None（None 时必须 Secure）
+pub static AUTH_COOKIE_SAME_SITE: Lazy<String> = Lazy::new(|| env_or("AUTH_COOKIE_SAME_SITE", "Lax".to_string()));
+
+/// cookie 的 Domain，为空时只发送给当前主机
+pub static AUTH_COOKIE_DOMAIN: Lazy<String> = Lazy::new(|| env_or("AUTH_COOKIE_DOMAIN", String::new()));
+
+/// 列表每页的最大条数，超过时按该值返回
+pub static MAX_PAGE_SIZE: Lazy<u64> = Lazy::new(|| env_or("MAX_PAGE_SIZE", 100));
+
+/// 额外的免登录路由，逗号分隔，格式见 `public_routes`，如 `GET /health,/docs/**`
+pub static PUBLIC_ROUTES: Lazy<String> = Lazy::new(|| env_or("PUBLIC_ROUTES", String::new()));
+
+/// 注册免登录的示例路由 `/`、`/hey`、`/echo`、`/query`、`/res`，仅用于本地调试
+pub static DEMO_ROUTES: Lazy<bool> = Lazy::new(|| env_or("DEMO_ROUTES", false));
+
+/// 启动时执行数据库迁移（建表并写入默认管理员和菜单），多实例部署时建议只在一个实例上开启
+pub static DB_AUTO_MIGRATE: Lazy<bool> = Lazy::new(|| env_or("DB_AUTO_MIGRATE", false));
+
+/// 可信的反向代理 IP 或 CIDR，逗号分隔，只信任来自这些地址的 `X-Forwarded-For`
+pub static TRUSTED_PROXIES: Lazy<String> = Lazy::new(|| env_or("TRUSTED_PROXIES", String::new()));
@@ -1,3 +1,15 @@
 pub mod result;
 pub mod security;
-pub mod simple_cache;
\ No newline at end of file
+pub mod rbac;
+pub mod perm_code;
+pub mod config;
+pub mod jwt_keys;
+pub mod auth_user;
+pub mod ttl_cache;
+pub mod password_policy;
+pub mod auth_cookie;
+pub mod public_routes;
+pub mod soft_delete;
+pub mod tree;
+pub mod pagination;
+pub mod client_ip;
@@ -0,0 +1,90 @@
+//! 列表分页和排序
+//!
+//! `pageIndex` 从 1 开始，传 0 按第 1 页处理；`pageSize` 不能为 0，超过 `MAX_PAGE_SIZE` 时按最大值返回。
+//! 需要全部记录的树形视图使用各自的 `/tree` 接口。
+//! `sort` 形如 `["orderNum", "-createdAt"]`，`-` 开头表示倒序，只能使用各列表允许的字段。
+
+use sea_orm::{DatabaseConnection, EntityTrait, Order, PaginatorTrait, QueryOrder, Select};
+use serde::Serialize;
+use crate::UserError;
+use crate::common::config::MAX_PAGE_SIZE;
+use crate::common::result::{FilterParam, PageResult};
+
+/// 列表允许的排序字段
+pub struct Sorting<C: 'static> {
+    /// 请求中的字段名和对应的列
+    pub columns: &'static [(&'static str, C)],
+    /// 未指定排序时使用，也追加在指定的排序之后，保证翻页时顺序稳定
+    pub default: &'static [(C, Order)],
+}
+
+pub async fn paginate<E, T>(db: &DatabaseConnection, mut select: Select<E>, page: &FilterParam<T>, sorting: &Sorting<E::Column>) -> Result<PageResult<E::Model>, UserError>
+where
+    E: EntityTrait,
+    E::Model: Serialize + Sync,
+{
+    let orders = parse_sort(&page.sort, sorting.columns)?;
+    for (col, order) in orders.into_iter().chain(sorting.default.iter().cloned()) {
+        select = select.order_by(col, order);
+    }
+
+    let (page_index, page_size) = page_window(page.page_index, page.page_size, *MAX_PAGE_SIZE)?;
+    let paginator = select.paginate(db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page_index - 1).await?;
+    Ok(PageResult::new(page_index, page_size, list, total))
+}
+
+/// 校验并规整页码和每页条数
+fn page_window(page_index: u64, page_size: u64, max_page_size: u64) -> Result<(u64, u64), UserError> {
+    if page_size == 0 {
+        return Err(UserError::ValidationError { field: "pageSize".to_string() });
+    }
+    let page_size = page_size.min(max_page_size);
+    let page_index = page_index.max(1);
+    if page_index.checked_mul(page_size).is_none() {
+        return Err(UserError::ValidationError { field: "pageIndex".to_string() });
+    }
+    Ok((page_index, page_size))
+}
+
+/// 把排序参数解析为列和顺序，字段不在 `columns` 中时报错
+fn parse_sort<C: Copy>(sort: &[String], columns: &[(&str, C)]) -> Result<Vec<(C, Order)>, UserError> {
+    sort.iter()
+        .map(|key| {
+            let (name, order) = match key.strip_prefix('-') {
+                Some(name) => (name, Order::Desc),
+                None => (key.as_str(), Order::Asc),
+            };
+            columns.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, col)| (*col, order))
+                .ok_or_else(|| UserError::ValidationError { field: format!("sort.{}", name) })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sort() {
+        let columns = [("id", 1), ("orderNum", 2)];
+        let sort = vec!["orderNum".to_string(), "-id".to_string()];
+        assert_eq!(parse_sort(&sort, &columns).unwrap(), vec![(2, Order::Asc), (1, Order::Desc)]);
+        assert!(parse_sort(&[], &columns).unwrap().is_empty());
+
+        let err = parse_sort(&["password".to_string()], &columns).unwrap_err();
+        assert_eq!(err.to_string(), "Validation error on field: sort.password");
+        assert!(parse_sort(&["--id".to_string()], &columns).is_err());
+    }
+
+    #[test]
+    fn test_page_window() {
+        assert_eq!(page_window(0, 10, 100).unwrap(), (1, 10));
+        assert_eq!(page_window(3, 1000, 100).unwrap(), (3, 100));
+        assert_eq!(page_window(1, 0, 100).unwrap_err().to_string(), "Validation error on field: pageSize");
+        assert!(page_window(u64::MAX, 100, 100).is_err());
+    }
+}
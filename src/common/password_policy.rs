@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use std::fs;
+use log::warn;
+use once_cell::sync::Lazy;
+use crate::UserError;
+use crate::common::config::env_or;
+
+/// 内置的常见弱密码，可通过 `PASSWORD_DENY_LIST_FILE`（每行一个）追加
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456", "12345678", "123456789", "1234567890", "password", "password1",
+    "p@ssw0rd", "passw0rd", "qwerty", "qwerty123", "abc123", "abc12345",
+    "111111", "000000", "123123", "654321", "iloveyou", "admin", "admin123",
+    "admin@123", "root", "root123", "welcome", "welcome1", "letmein", "1q2w3e4r",
+    "1qaz2wsx", "qazwsx", "zxcvbnm", "aa123456", "a123456", "changeme",
+];
+
+pub static PASSWORD_POLICY: Lazy<PasswordPolicy> = Lazy::new(PasswordPolicy::from_env);
+
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    /// 至少包含几类字符（小写、大写、数字、符号）
+    pub min_char_classes: usize,
+    pub disallow_username: bool,
+    pub deny_list: HashSet<String>,
+}
+
+impl PasswordPolicy {
+
+    fn from_env() -> Self {
+        let mut deny_list: HashSet<String> = COMMON_PASSWORDS.iter().map(|s| s.to_string()).collect();
+        let file = env_or("PASSWORD_DENY_LIST_FILE", String::new());
+        if !file.is_empty() {
+            match fs::read_to_string(&file) {
+                Ok(content) => deny_list.extend(
+                    content.lines()
+                        .map(|l| l.trim().to_lowercase())
+                        .filter(|l| !l.is_empty())
+                ),
+                Err(e) => warn!("read password deny list {} error: {}", file, e),
+            }
+        }
+        PasswordPolicy {
+            min_length: env_or("PASSWORD_MIN_LENGTH", 8),
+            min_char_classes: env_or("PASSWORD_MIN_CHAR_CLASSES", 3),
+            disallow_username: env_or("PASSWORD_DISALLOW_USERNAME", true),
+            deny_list,
+        }
+    }
+
+    pub fn validate(&self, user_name: &str, password: &str) -> Result<(), UserError> {
+        if password.chars().count() < self.min_length {
+            return Err(UserError::WeakPassword(format!("password must be at least {} characters", self.min_length)));
+        }
+        let classes = [
+            password.chars().any(|c| c.is_lowercase()),
+            password.chars().any(|c| c.is_uppercase()),
+            password.chars().any(|c| c.is_ascii_digit()),
+            password.chars().any(|c| !c.is_alphanumeric()),
+        ].iter().filter(|b| **b).count();
+        if classes < self.min_char_classes {
+            return Err(UserError::WeakPassword(format!(
+                "password must contain at least {} of lowercase, uppercase, digit and symbol", self.min_char_classes
+            )));
+        }
+        let lower = password.to_lowercase();
+        if self.disallow_username && !user_name.is_empty() && lower.contains(&user_name.to_lowercase()) {
+            return Err(UserError::WeakPassword("password must not contain the user name".to_string()));
+        }
+        if self.deny_list.contains(&lower) {
+            return Err(UserError::WeakPassword("password is too common".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::common::password_policy::PasswordPolicy;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 8,
+            min_char_classes: 3,
+            disallow_username: true,
+            deny_list: HashSet::from(["p@ssw0rd1".to_string()]),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let policy = policy();
+        assert!(policy.validate("admin", "Xy7#kq9!").is_ok());
+        assert!(policy.validate("admin", "").is_err());
+        assert!(policy.validate("admin", "Xy7#kq9").is_err());
+        assert!(policy.validate("admin", "abcdefgh1").is_err());
+        assert!(policy.validate("admin", "MyAdmin#2024").is_err());
+        assert!(policy.validate("admin", "P@ssw0rd1").is_err());
+    }
+}
@@ -0,0 +1,33 @@
+//! 权限码，与前端 ng-antd-admin 菜单/按钮的 code 保持一致
+
+pub const MENU: &str = "default:system:menu";
+pub const MENU_ADD: &str = "default:system:menu:add";
+pub const MENU_EDIT: &str = "default:system:menu:edit";
+pub const MENU_DEL: &str = "default:system:menu:del";
+
+pub const ACCOUNT: &str = "default:system:account";
+pub const ACCOUNT_ADD: &str = "default:system:account:add";
+pub const ACCOUNT_EDIT: &str = "default:system:account:edit";
+pub const ACCOUNT_DEL: &str = "default:system:account:del";
+pub const ACCOUNT_IMPERSONATE: &str = "default:system:account:impersonate";
+
+pub const ROLE: &str = "default:system:role-manager";
+pub const ROLE_ADD: &str = "default:system:role-manager:add";
+pub const ROLE_EDIT: &str = "default:system:role-manager:edit";
+pub const ROLE_DEL: &str = "default:system:role-manager:del";
+pub const ROLE_SET_ROLE: &str = "default:system:role-manager:set-role";
+
+pub const DEPT: &str = "default:system:dept";
+pub const DEPT_ADD: &str = "default:system:dept:add";
+pub const DEPT_EDIT: &str = "default:system:dept:edit";
+pub const DEPT_DEL: &str = "default:system:dept:del";
+
+pub const API_KEY: &str = "default:system:api-key";
+pub const API_KEY_ADD: &str = "default:system:api-key:add";
+pub const API_KEY_DEL: &str = "default:system:api-key:del";
+
+pub const OAUTH_CLIENT: &str = "default:system:oauth-client";
+pub const OAUTH_CLIENT_ADD: &str = "default:system:oauth-client:add";
+pub const OAUTH_CLIENT_DEL: &str = "default:system:oauth-client:del";
+
+pub const AUDIT_LOG: &str = "default:system:audit-log";
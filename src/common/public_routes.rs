@@ -0,0 +1,226 @@
+//! 免登录路由
+//!
+//! 每条规则为 `[方法] 路径`，方法可省略（匹配所有方法）或用 `|` 分隔多个，如 `GET|POST /oauth/userinfo`。
+//! 路径中 `*` 匹配一段，结尾的 `/**` 匹配该前缀下的所有路径。
+//! 内置规则之外可以通过 `PUBLIC_ROUTES` 追加，启动时会与已注册的路由比对，写错直接退出。
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use actix_http::Request;
+use actix_service::IntoServiceFactory;
+use actix_web::{App, HttpRequest, HttpResponse};
+use actix_web::dev::{AppConfig, Service, ServiceFactory, ServiceRequest};
+use actix_web::http::Method;
+use actix_web::web::ServiceConfig;
+use futures::future::ready;
+use log::warn;
+use once_cell::sync::Lazy;
+use crate::common::config::{DEMO_ROUTES, PUBLIC_ROUTES};
+
+/// `DEMO_ROUTES` 开启时注册的示例路由
+pub const DEMO_PUBLIC_ROUTES: &[&str] = &[
+    "GET /",
+    "GET /hey",
+    "POST /echo",
+    "GET /query",
+    "GET /res",
+];
+
+const DEFAULT_PUBLIC_ROUTES: &[&str] = &[
+    "POST /auth/signin",
+    "POST /auth/refresh",
+    "POST /auth/password/expired",
+    "POST /auth/password/forgot",
+    "POST /auth/password/reset",
+    "POST /auth/mfa/verify",
+    "GET /auth/oidc/authorize",
+    "POST /auth/oidc/callback",
+    "GET /.well-known/jwks.json",
+    "GET /.well-known/openid-configuration",
+    "GET /oauth/authorize",
+    "POST /oauth/token",
+    "POST /oauth/introspect",
+    "POST /oauth/revoke",
+    "GET /oauth/userinfo",
+];
+
+/// 启动时解析，配置错误直接退出
+pub static PUBLIC_ROUTE_RULES: Lazy<PublicRoutes> = Lazy::new(|| {
+    let demo = if *DEMO_ROUTES { DEMO_PUBLIC_ROUTES } else { &[] };
+    let extra = PUBLIC_ROUTES.split(',').map(str::trim).filter(|s| !s.is_empty());
+    PublicRoutes::parse(DEFAULT_PUBLIC_ROUTES.iter().chain(demo).copied().chain(extra))
+        .unwrap_or_else(|e| panic!("PUBLIC_ROUTES is invalid: {}", e))
+});
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Any,
+}
+
+#[derive(Debug)]
+pub struct RoutePattern {
+    raw: String,
+    /// None 表示所有方法
+    methods: Option<Vec<Method>>,
+    segments: Vec<Segment>,
+    /// 以 `/**` 结尾，匹配前缀
+    prefix: bool,
+}
+
+impl RoutePattern {
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let (methods, path) = match raw.split_once(char::is_whitespace) {
+            Some((methods, path)) => {
+                let methods = methods.split('|')
+                    .map(|m| Method::from_bytes(m.to_uppercase().as_bytes()).map_err(|_| format!("invalid method in {}", raw)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                (Some(methods), path.trim())
+            }
+            None => (None, raw),
+        };
+        if !path.starts_with('/') {
+            return Err(format!("path of {} must start with /", raw));
+        }
+        let mut segments: Vec<&str> = path[1..].split('/').collect();
+        let prefix = segments.last() == Some(&"**");
+        if prefix {
+            segments.pop();
+        }
+        let segments = segments.into_iter()
+            .map(|s| match s {
+                "*" => Ok(Segment::Any),
+                s if s.contains('*') => Err(format!("wildcard must be a whole segment in {}", raw)),
+                s => Ok(Segment::Literal(s.to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RoutePattern { raw: raw.to_string(), methods, segments, prefix })
+    }
+
+    pub fn matches(&self, method: &Method, path: &str) -> bool {
+        if let Some(methods) = &self.methods {
+            if !methods.contains(method) {
+                return false;
+            }
+        }
+        let Some(path) = path.strip_prefix('/') else {
+            return false;
+        };
+        let parts: Vec<&str> = path.split('/').collect();
+        let len = self.segments.len();
+        if parts.len() < len || (!self.prefix && parts.len() > len) {
+            return false;
+        }
+        // 按段比较，`/a/**` 不会匹配 `/ab`
+        self.segments.iter().zip(parts.iter()).all(|(segment, part)| match segment {
+            Segment::Any => !part.is_empty(),
+            Segment::Literal(s) => s == part,
+        })
+    }
+
+    /// 用于启动时比对的示例路径，`*` 替换为 `0`
+    fn probe_path(&self) -> String {
+        let path = self.segments.iter()
+            .map(|s| match s {
+                Segment::Any => "0",
+                Segment::Literal(s) => s.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        format!("/{}", path)
+    }
+}
+
+#[derive(Debug)]
+pub struct PublicRoutes {
+    patterns: Vec<RoutePattern>,
+}
+
+impl PublicRoutes {
+
+    pub fn parse<'a>(rules: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let patterns = rules.into_iter().map(RoutePattern::parse).collect::<Result<Vec<_>, _>>()?;
+        Ok(PublicRoutes { patterns })
+    }
+
+    pub fn is_public(&self, method: &Method, path: &str) -> bool {
+        self.patterns.iter().any(|p| p.matches(method, path))
+    }
+
+    /// 与已注册的路由路径比对。路由表不记录方法，只校验路径。
+    /// 前缀规则无法列举其下的路由，示例路径不存在时只打印警告。
+    pub async fn validate(&self, configure: fn(&mut ServiceConfig)) -> Result<(), String> {
+        let req = registered_routes(configure).await?;
+        let routes = req.resource_map();
+        let mut unknown = Vec::new();
+        for pattern in &self.patterns {
+            let path = pattern.probe_path();
+            match (routes.has_resource(&path), pattern.prefix) {
+                (true, _) => {}
+                (false, true) => warn!("public route {} can not be validated, no route matches {}", pattern.raw, path),
+                (false, false) => unknown.push(pattern.raw.clone()),
+            }
+        }
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("unknown public routes: {}", unknown.join(", ")))
+        }
+    }
+}
+
+/// 取出应用的路由表（挂在请求上）。请求在应用级中间件中直接返回，不会进入路由和处理函数
+async fn registered_routes(configure: fn(&mut ServiceConfig)) -> Result<HttpRequest, String> {
+    let captured = Rc::new(RefCell::new(None));
+    let sink = Rc::clone(&captured);
+    let app = App::new()
+        .wrap_fn(move |req: ServiceRequest, _| {
+            sink.replace(Some(req.request().clone()));
+            ready(Ok(req.into_response(HttpResponse::NoContent().finish())))
+        })
+        .configure(configure);
+    let service = app.into_factory()
+        .new_service(AppConfig::default())
+        .await
+        .map_err(|_| "failed to build routes".to_string())?;
+    service.call(Request::new()).await.map_err(|e| e.to_string())?;
+    captured.take().ok_or_else(|| "failed to read routes".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let routes = PublicRoutes::parse(["POST /auth/signin", "GET|HEAD /user/*/avatar", "/docs/**"]).unwrap();
+        assert!(routes.is_public(&Method::POST, "/auth/signin"));
+        assert!(!routes.is_public(&Method::GET, "/auth/signin"));
+        assert!(!routes.is_public(&Method::POST, "/auth/signin/x"));
+        assert!(routes.is_public(&Method::HEAD, "/user/1/avatar"));
+        assert!(!routes.is_public(&Method::GET, "/user//avatar"));
+        assert!(routes.is_public(&Method::DELETE, "/docs/a/b"));
+        assert!(routes.is_public(&Method::GET, "/docs"));
+        assert!(!routes.is_public(&Method::GET, "/docsx"));
+        assert!(RoutePattern::parse("GET auth").is_err());
+        assert!(RoutePattern::parse("/user/a*").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_validate() {
+        let routes = PublicRoutes::parse(DEFAULT_PUBLIC_ROUTES.iter().copied()).unwrap();
+        assert!(routes.validate(crate::init_service).await.is_ok());
+        // 未开启 DEMO_ROUTES 时示例路由没有注册
+        let routes = PublicRoutes::parse(DEMO_PUBLIC_ROUTES.iter().copied()).unwrap();
+        assert!(routes.validate(crate::init_service).await.is_err());
+        fn configure(cfg: &mut ServiceConfig) {
+            crate::api::dispatch(cfg);
+        }
+        let routes = PublicRoutes::parse(["POST /auth/signin", "GET /oauth/authorize", "GET /user/auth-code/*"]).unwrap();
+        assert!(routes.validate(configure).await.is_ok());
+        let routes = PublicRoutes::parse(["/auth/nothing"]).unwrap();
+        assert!(routes.validate(configure).await.is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! 路由级权限校验
+//!
+//! `validator` 只负责校验 token 并把 `Claims` 放进 request extensions，
+//! 权限码不取 token 中的 `perms`，而是按用户当前的角色查询（见 `AuthUser::load`），
+//! 收回角色或权限后不必等 token 过期。
+//! 需要权限的路由在宏上声明所需权限码，例如：
+//!
+//! ```ignore
+//! #[post("/create", wrap = "Perm::require(perm_code::ACCOUNT_ADD)")]
+//! ```
+//!
+//! 未声明权限码的路由只要求登录。
+
+use std::rc::Rc;
+use actix_web::body::MessageBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures::future::{ready, LocalBoxFuture, Ready};
+use log::warn;
+use crate::UserError;
+use crate::common::auth_user::AuthUser;
+
+pub struct Perm {
+    code: &'static str,
+}
+
+impl Perm {
+    pub fn require(code: &'static str) -> Self {
+        Perm { code }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Perm
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = PermMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(PermMiddleware {
+            service: Rc::new(service),
+            code: self.code,
+        }))
+    }
+}
+
+pub struct PermMiddleware<S> {
+    service: Rc<S>,
+    code: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for PermMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let code = self.code;
+        Box::pin(async move {
+            let user = AuthUser::from_req(req.request()).await?;
+            if !user.has_perm(code) {
+                warn!("user {} has no permission {} for {}", user.user_name, code, req.path());
+                return Err(UserError::Forbidden(format!("permission denied: {}", code)).into());
+            }
+            service.call(req).await
+        })
+    }
+}
@@ -1,8 +1,21 @@
-use actix_web::body::MessageBody;
 use actix_web::web::Json;
 use serde::{Deserialize, Serialize};
 use derive_more::Display;
-use crate::service::menu_service::SearchParams;
+
+/// 密码不符合密码策略
+pub const CODE_WEAK_PASSWORD: u16 = 4001;
+/// 用户名或密码错误
+pub const CODE_BAD_CREDENTIALS: u16 = 4011;
+/// 两步验证码错误
+pub const CODE_INVALID_MFA_CODE: u16 = 4012;
+/// 账号已被禁用
+pub const CODE_ACCOUNT_DISABLED: u16 = 4031;
+/// 密码已过期，需要修改密码后再登录
+pub const CODE_PASSWORD_EXPIRED: u16 = 4032;
+/// 账号因多次登录失败被临时锁定
+pub const CODE_ACCOUNT_LOCKED: u16 = 4231;
+/// 同一 IP 登录失败次数过多
+pub const CODE_TOO_MANY_ATTEMPTS: u16 = 4291;
 
 #[derive(Serialize, Debug)]
 pub struct CommonResult<T> {
@@ -57,7 +70,10 @@ pub struct FilterParam<T> {
     pub page_index:u64,
     #[serde(rename(deserialize = "pageSize", serialize = "pageSize"))]
     pub page_size:u64,
-    pub filters:Option<T>
+    pub filters:Option<T>,
+    /// 排序字段，见 `pagination` 模块
+    #[serde(default)]
+    pub sort:Vec<String>,
 }
 
 #[derive(Serialize,Debug)]
@@ -0,0 +1,106 @@
+//! 软删除
+//!
+//! 删除时只写 `deleted_at`，查询统一从 `active()` 开始以排除已删除的记录。
+//! 关联记录和主记录使用同一个删除时间，恢复时按这个时间一起恢复。
+
+use sea_orm::{ColumnTrait, DeleteMany, EntityTrait, PrimaryKeyTrait, QueryFilter, Select, UpdateMany};
+use sea_orm::prelude::DateTime;
+use sea_orm::sea_query::Expr;
+use crate::entity::{department, menu, role, sys_role_perm, sys_user_role, user};
+
+pub trait SoftDelete: EntityTrait {
+    fn deleted_at() -> Self::Column;
+
+    /// 未删除的记录
+    fn active() -> Select<Self> {
+        Self::find().filter(Self::deleted_at().is_null())
+    }
+
+    fn active_by_id<T>(id: T) -> Select<Self>
+    where T: Into<<Self::PrimaryKey as PrimaryKeyTrait>::ValueType> {
+        Self::find_by_id(id).filter(Self::deleted_at().is_null())
+    }
+
+    /// 回收站中的记录
+    fn trashed() -> Select<Self> {
+        Self::find().filter(Self::deleted_at().is_not_null())
+    }
+
+    /// 把未删除的记录标记为在 `at` 删除，由调用方追加过滤条件
+    fn soft_delete(at: DateTime) -> UpdateMany<Self> {
+        Self::update_many()
+            .col_expr(Self::deleted_at(), Expr::value(at))
+            .filter(Self::deleted_at().is_null())
+    }
+
+    /// 恢复回收站中的记录，由调用方追加过滤条件
+    fn restore() -> UpdateMany<Self> {
+        Self::update_many()
+            .col_expr(Self::deleted_at(), Expr::value(Option::<DateTime>::None))
+            .filter(Self::deleted_at().is_not_null())
+    }
+
+    /// 彻底删除回收站中的记录
+    fn purge() -> DeleteMany<Self> {
+        Self::delete_many().filter(Self::deleted_at().is_not_null())
+    }
+}
+
+impl SoftDelete for user::Entity {
+    fn deleted_at() -> Self::Column {
+        user::Column::DeletedAt
+    }
+}
+
+impl SoftDelete for role::Entity {
+    fn deleted_at() -> Self::Column {
+        role::Column::DeletedAt
+    }
+}
+
+impl SoftDelete for menu::Entity {
+    fn deleted_at() -> Self::Column {
+        menu::Column::DeletedAt
+    }
+}
+
+impl SoftDelete for department::Entity {
+    fn deleted_at() -> Self::Column {
+        department::Column::DeletedAt
+    }
+}
+
+impl SoftDelete for sys_role_perm::Entity {
+    fn deleted_at() -> Self::Column {
+        sys_role_perm::Column::DeletedAt
+    }
+}
+
+impl SoftDelete for sys_user_role::Entity {
+    fn deleted_at() -> Self::Column {
+        sys_user_role::Column::DeletedAt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{DbBackend, QueryTrait};
+    use sea_orm::sqlx::types::chrono::NaiveDate;
+    use super::*;
+
+    #[test]
+    fn test_queries() {
+        let sql = role::Entity::active_by_id(1).build(DbBackend::Postgres).to_string();
+        assert!(sql.ends_with(r#"WHERE "role"."id" = 1 AND "role"."deleted_at" IS NULL"#), "{}", sql);
+
+        let at = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap().and_hms_opt(3, 4, 5).unwrap();
+        let sql = sys_user_role::Entity::soft_delete(at)
+            .filter(sys_user_role::Column::UserId.eq(7))
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert_eq!(sql, r#"UPDATE "sys_user_role" SET "deleted_at" = '2024-01-02 03:04:05.000000' WHERE "sys_user_role"."deleted_at" IS NULL AND "sys_user_role"."user_id" = 7"#);
+
+        let sql = user::Entity::restore().build(DbBackend::Postgres).to_string();
+        assert_eq!(sql, r#"UPDATE "user" SET "deleted_at" = NULL WHERE "user"."deleted_at" IS NOT NULL"#);
+    }
+}
@@ -0,0 +1,88 @@
+//! 按 `father_id` 组装树形结构，菜单和部门共用
+
+use std::collections::HashMap;
+use serde::Serialize;
+
+/// 可以组成树的记录，上级 id 为 0 表示根节点
+pub trait TreeItem {
+    fn id(&self) -> i32;
+    fn father_id(&self) -> i32;
+    /// 同级之间的排序
+    fn order_num(&self) -> i32;
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TreeNode<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub children: Vec<TreeNode<T>>,
+}
+
+/// 从 `root` 的直接下级开始组装，同级按 `order_num`、`id` 排序。
+/// 上级不在列表中的记录连同其下级一起丢弃，成环的记录也不会出现在结果中
+pub fn build_tree<T: TreeItem>(items: Vec<T>, root: i32) -> Vec<TreeNode<T>> {
+    let mut children: HashMap<i32, Vec<T>> = HashMap::new();
+    for item in items {
+        children.entry(item.father_id()).or_default().push(item);
+    }
+    take_children(&mut children, root)
+}
+
+fn take_children<T: TreeItem>(children: &mut HashMap<i32, Vec<T>>, father_id: i32) -> Vec<TreeNode<T>> {
+    let Some(mut items) = children.remove(&father_id) else {
+        return Vec::new();
+    };
+    items.sort_by_key(|item| (item.order_num(), item.id()));
+    items
+        .into_iter()
+        .map(|item| {
+            let children = take_children(children, item.id());
+            TreeNode { item, children }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item(i32, i32, i32);
+
+    impl TreeItem for Item {
+        fn id(&self) -> i32 {
+            self.0
+        }
+        fn father_id(&self) -> i32 {
+            self.1
+        }
+        fn order_num(&self) -> i32 {
+            self.2
+        }
+    }
+
+    fn ids(nodes: &[TreeNode<Item>]) -> Vec<i32> {
+        nodes.iter().map(|n| n.item.0).collect()
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let tree = build_tree(vec![
+            Item(4, 1, 2),
+            Item(1, 0, 1),
+            Item(2, 0, 0),
+            Item(3, 1, 1),
+            // 上级 5 不在列表中
+            Item(6, 5, 0),
+            Item(7, 6, 0),
+            // 自己是自己的上级
+            Item(8, 8, 0),
+        ], 0);
+        assert_eq!(ids(&tree), vec![2, 1]);
+        assert!(tree[0].children.is_empty());
+        assert_eq!(ids(&tree[1].children), vec![3, 4]);
+
+        let tree = build_tree(vec![Item(6, 5, 0), Item(7, 6, 0)], 5);
+        assert_eq!(ids(&tree), vec![6]);
+        assert_eq!(ids(&tree[0].children), vec![7]);
+    }
+}
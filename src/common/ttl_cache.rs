@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// 带过期时间的内存缓存，用于减少每个请求的数据库查询
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    data: RwLock<HashMap<K, (Instant, V)>>,
+}
+
+impl<K, V> TtlCache<K, V> where K: Eq + Hash, V: Clone {
+
+    pub fn new(ttl: Duration) -> Self {
+        TtlCache {
+            ttl,
+            data: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let data = self.data.read().unwrap();
+        data.get(key)
+            .filter(|(at, _)| at.elapsed() < self.ttl)
+            .map(|(_, v)| v.clone())
+    }
+
+    pub fn set(&self, key: K, value: V) {
+        let mut data = self.data.write().unwrap();
+        // 顺便清理过期的数据，避免无限增长
+        data.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        data.insert(key, (Instant::now(), value));
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let mut data = self.data.write().unwrap();
+        data.remove(key).map(|(_, v)| v)
+    }
+
+    pub fn clear(&self) {
+        self.data.write().unwrap().clear();
+    }
+}
@@ -14,6 +14,9 @@ pub struct Model {
     pub department_name: Option<String>,
     pub order_num: Option<i32>,
     pub state: Option<bool>,
+    /// 物化路径 `/1/3/7/`，包含自身
+    #[serde(skip_deserializing)]
+    pub path: String,
     pub updated_at: Option<DateTime>,
     #[serde(skip_deserializing)] // Skip deserializing
     pub created_at: DateTime,
@@ -5,6 +5,21 @@ pub mod prelude;
 pub mod department;
 pub mod menu;
 pub mod role;
+pub mod sys_api_key;
+pub mod sys_audit_log;
+pub mod sys_login_attempt;
+pub mod sys_mfa_recovery_code;
+pub mod sys_oauth_client;
+pub mod sys_oauth_code;
+pub mod sys_oauth_token;
+pub mod sys_oidc_login_state;
+pub mod sys_password_history;
+pub mod sys_password_reset_token;
+pub mod sys_refresh_token;
+pub mod sys_revoked_token;
 pub mod sys_role_perm;
+pub mod sys_user_identity;
+pub mod sys_user_mfa;
 pub mod sys_user_role;
+pub mod sys_user_session;
 pub mod user;
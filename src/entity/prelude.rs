@@ -3,6 +3,21 @@
 pub use super::department::Entity as Department;
 pub use super::menu::Entity as Menu;
 pub use super::role::Entity as Role;
+pub use super::sys_api_key::Entity as SysApiKey;
+pub use super::sys_audit_log::Entity as SysAuditLog;
+pub use super::sys_login_attempt::Entity as SysLoginAttempt;
+pub use super::sys_mfa_recovery_code::Entity as SysMfaRecoveryCode;
+pub use super::sys_oauth_client::Entity as SysOauthClient;
+pub use super::sys_oauth_code::Entity as SysOauthCode;
+pub use super::sys_oauth_token::Entity as SysOauthToken;
+pub use super::sys_oidc_login_state::Entity as SysOidcLoginState;
+pub use super::sys_password_history::Entity as SysPasswordHistory;
+pub use super::sys_password_reset_token::Entity as SysPasswordResetToken;
+pub use super::sys_refresh_token::Entity as SysRefreshToken;
+pub use super::sys_revoked_token::Entity as SysRevokedToken;
 pub use super::sys_role_perm::Entity as SysRolePerm;
+pub use super::sys_user_identity::Entity as SysUserIdentity;
+pub use super::sys_user_mfa::Entity as SysUserMfa;
 pub use super::sys_user_role::Entity as SysUserRole;
+pub use super::sys_user_session::Entity as SysUserSession;
 pub use super::user::Entity as User;
@@ -11,6 +11,8 @@ pub struct Model {
     pub id: i32,
     pub role_name: String,
     pub role_desc: Option<String>,
+    /// 该角色的用户登录时必须通过两步验证
+    pub require_mfa: bool,
     pub updated_at: Option<DateTime>,
     pub created_at: DateTime,
     pub deleted_at: Option<DateTime>,
@@ -14,11 +14,17 @@ pub struct Model {
     #[serde(skip_serializing)]
     pub password: String,
     pub available: bool,
+    /// 服务账号只能通过 API key 调用接口，不能用密码登录
+    pub service_account: bool,
+    /// 指定的认证方式（local、ldap），为空时按 `AUTH_PROVIDER_CHAIN` 依次尝试
+    pub auth_provider: Option<String>,
     pub sex: i32,
     pub mobile: String,
     pub telephone: Option<String>,
     pub department_id: i32,
     pub last_login_time: Option<DateTime>,
+    pub last_login_ip: Option<String>,
+    pub password_changed_at: Option<DateTime>,
     pub updated_at: Option<DateTime>,
     pub created_at: DateTime,
     pub deleted_at: Option<DateTime>,
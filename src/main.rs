@@ -4,29 +4,43 @@ mod entity;
 mod api;
 
 use std::env;
-use std::fmt::Formatter;
+use std::sync::Arc;
 use std::time::Duration;
-use actix_web::{get, post, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use actix_web::{get, post, web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder};
 use actix_web::dev::ServiceRequest;
-use actix_web::http::header::ContentType;
+use actix_web::http::header::{ContentType, RETRY_AFTER};
 use actix_web::http::StatusCode;
 use actix_web::web::Query;
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use actix_web_httpauth::middleware::HttpAuthentication;
-use derive_more::Display;
-use env_logger::Builder;
 use log::{error, info, warn};
+use once_cell::sync::Lazy;
 use serde::Deserialize;
 use thiserror::Error;
+use migration::{Migrator, MigratorTrait};
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 use sea_orm::DbErr;
-use serde_json::Error;
+use crate::common::result;
 use crate::common::result::CommonResult;
-use crate::common::security::{Claims, Security};
+use crate::common::config::{DB_AUTO_MIGRATE, DEMO_ROUTES, REVOKED_TOKEN_PURGE_INTERVAL_SECS};
+use crate::common::auth_cookie;
+use crate::common::client_ip::{client_ip, TRUSTED_PROXY_NETS};
+use crate::common::jwt_keys::JWT_KEYS;
+use crate::common::public_routes::PUBLIC_ROUTE_RULES;
+use crate::common::security::Security;
+use crate::service::api_key_service::ApiKeyService;
+use crate::service::auth::Auth;
+use crate::service::auth_provider::{create_auth_providers, AuthProviders};
+use crate::service::login_attempt_service::{create_login_attempt_store, LoginAttemptStore};
+use crate::service::mail_service::{create_mail_sender, MailSender};
+use crate::service::token_revocation_service::TokenRevocationService;
 
 #[derive(Debug, Clone)]
 struct AppState {
     conn: DatabaseConnection,
+    login_attempts: Arc<dyn LoginAttemptStore>,
+    mailer: Arc<dyn MailSender>,
+    auth_providers: Arc<AuthProviders>,
 }
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -37,7 +51,13 @@ async fn main() -> std::io::Result<()> {
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set in .env file");
     let host = env::var("HOST").expect("HOST is not set in .env file");
     let port = env::var("PORT").expect("PORT is not set in .env file");
-    let _ = env::var("SECRET_KEY").expect("SECRET_KEY is not set in .env file");
+    // 启动时加载 JWT 密钥，配置错误直接退出
+    Lazy::force(&JWT_KEYS);
+    // 免登录路由必须是已注册的路由
+    if let Err(e) = PUBLIC_ROUTE_RULES.validate(init_service).await {
+        panic!("{}", e);
+    }
+    Lazy::force(&TRUSTED_PROXY_NETS);
     let server_url = format!("{host}:{port}");
 
     let mut opt = ConnectOptions::new(&db_url);
@@ -50,18 +70,22 @@ async fn main() -> std::io::Result<()> {
         // .sqlx_logging(true)
         .sqlx_logging_level(log::LevelFilter::Debug);
     let db = Database::connect(opt).await.unwrap();
-    let state = AppState {conn: db };
+    if *DB_AUTO_MIGRATE {
+        Migrator::up(&db, None).await.expect("database migration failed");
+    }
+    spawn_revoked_token_purge(db.clone());
+    let state = AppState {
+        login_attempts: create_login_attempt_store(db.clone()),
+        mailer: create_mail_sender(),
+        auth_providers: create_auth_providers(),
+        conn: db,
+    };
 
     let server = HttpServer::new(move|| {
         let auth = HttpAuthentication::with_fn(validator);
         App::new()
             .app_data(web::Data::new(state.clone()))
-            .service(hello)
-            .service(echo)
-            .service(query)
-            .service(test)
             .wrap(auth)
-            .route("/hey", web::get().to(manual_hello))
             .app_data(web::JsonConfig::default().limit(1024 * 1024 * 3).error_handler(handle_json_error))
             .configure(init_service)
     }).workers(3).bind(&server_url);
@@ -74,7 +98,29 @@ async fn main() -> std::io::Result<()> {
     Ok(())
 }
 
+// 定时清理已过期的 token 吊销记录
+fn spawn_revoked_token_purge(conn: DatabaseConnection) {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(*REVOKED_TOKEN_PURGE_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            match TokenRevocationService::purge_expired(&conn).await {
+                Ok(n) => info!("purged {} expired revoked tokens", n),
+                Err(e) => error!("purge revoked tokens error: {}", e),
+            }
+        }
+    });
+}
+
 fn init_service(cfg: &mut web::ServiceConfig) {
+    // 示例路由免登录，只在显式开启时注册
+    if *DEMO_ROUTES {
+        cfg.service(hello)
+            .service(echo)
+            .service(query)
+            .service(test)
+            .route("/hey", web::get().to(manual_hello));
+    }
     api::dispatch(cfg);
 }
 
@@ -82,7 +128,7 @@ fn init_service(cfg: &mut web::ServiceConfig) {
 fn handle_json_error(err: actix_web::error::JsonPayloadError, _req: &HttpRequest)->actix_web::Error {
     // 在这里处理 JSON payload 错误，例如返回适当的错误响应或记录错误日志
     let msg = CommonResult::<String>::fail(400, format!("JSON deserialization error: {}", err)).to_string();
-    actix_web::error::InternalError::from_response(err, HttpResponse::BadRequest().body(msg).into()).into()
+    actix_web::error::InternalError::from_response(err, HttpResponse::BadRequest().body(msg)).into()
 }
 
 
@@ -95,6 +141,26 @@ enum UserError {
     #[error("json paser error")]
     JsonErr(#[from] serde_json::Error),
     #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("user name or password is incorrect")]
+    BadCredentials,
+    #[error("account is disabled")]
+    AccountDisabled,
+    #[error("account is locked, retry after {0} seconds")]
+    AccountLocked(i64),
+    #[error("{0}")]
+    WeakPassword(String),
+    #[error("mfa code is invalid")]
+    InvalidMfaCode,
+    #[error("password is expired")]
+    PasswordExpired,
+    #[error("too many failed attempts, retry after {0} seconds")]
+    TooManyAttempts(i64),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
     Error(String),
 }
 
@@ -103,9 +169,19 @@ impl actix_web::error::ResponseError for UserError {
     fn status_code(&self) -> StatusCode {
         match *self {
             UserError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            UserError::WeakPassword(_) => StatusCode::BAD_REQUEST,
             UserError::JsonErr(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            UserError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            UserError::Forbidden(_) => StatusCode::FORBIDDEN,
+            UserError::Conflict(_) => StatusCode::CONFLICT,
+            UserError::BadCredentials => StatusCode::UNAUTHORIZED,
+            UserError::InvalidMfaCode => StatusCode::UNAUTHORIZED,
+            UserError::AccountDisabled => StatusCode::FORBIDDEN,
+            UserError::PasswordExpired => StatusCode::FORBIDDEN,
+            UserError::AccountLocked(_) => StatusCode::LOCKED,
+            UserError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
             _ => {
-                println!("User Error: {}", "????");
+                println!("User Error: ????");
                 StatusCode::INTERNAL_SERVER_ERROR
             },
         }
@@ -115,40 +191,92 @@ impl actix_web::error::ResponseError for UserError {
             UserError::ValidationError { field: _ } => self.to_string(),
             UserError::DbErr(e) => e.to_string(),
             UserError::JsonErr(e) => e.to_string(),
+            UserError::Unauthorized(e) => e.to_string(),
+            UserError::Forbidden(e) => e.to_string(),
+            UserError::Conflict(e) => e.to_string(),
+            UserError::WeakPassword(e) => e.to_string(),
+            UserError::BadCredentials
+            | UserError::InvalidMfaCode
+            | UserError::AccountDisabled
+            | UserError::AccountLocked(_)
+            | UserError::PasswordExpired
+            | UserError::TooManyAttempts(_) => self.to_string(),
             UserError::Error(e) => e.to_string(),
-            _ => "Unknown Error".to_string(),
         };
-        let res = CommonResult::<String>::fail(400, msg).to_string();
-        HttpResponse::build(self.status_code())
+        let code = match self {
+            UserError::Unauthorized(_) | UserError::Forbidden(_) | UserError::Conflict(_) => self.status_code().as_u16(),
+            UserError::BadCredentials => result::CODE_BAD_CREDENTIALS,
+            UserError::InvalidMfaCode => result::CODE_INVALID_MFA_CODE,
+            UserError::AccountDisabled => result::CODE_ACCOUNT_DISABLED,
+            UserError::AccountLocked(_) => result::CODE_ACCOUNT_LOCKED,
+            UserError::TooManyAttempts(_) => result::CODE_TOO_MANY_ATTEMPTS,
+            UserError::WeakPassword(_) => result::CODE_WEAK_PASSWORD,
+            UserError::PasswordExpired => result::CODE_PASSWORD_EXPIRED,
+            _ => 400,
+        };
+        let res = CommonResult::<String>::fail(code, msg).to_string();
+        let mut builder = HttpResponse::build(self.status_code());
+        if let UserError::AccountLocked(secs) | UserError::TooManyAttempts(secs) = self {
+            builder.insert_header((RETRY_AFTER, secs.to_string()));
+        }
+        builder
             .insert_header(ContentType::json())
             .body(res)
     }
 }
 
-fn excluded_routes()->Vec<&'static str> {
-    vec![
-        "/auth/signin"
-    ]
-}
+const API_KEY_HEADER: &str = "X-API-Key";
 
 async fn validator(
     req: ServiceRequest,
     credentials: Option<BearerAuth>,
 ) -> Result<ServiceRequest, (actix_web::Error, ServiceRequest)> {
-    let path = req.path().to_string();
-    let routes = excluded_routes();
-    if routes.contains(&path.as_str()) {
+    if PUBLIC_ROUTE_RULES.is_public(req.method(), req.path()) {
+        return Ok(req);
+    }
+    let Some(state) = req.app_data::<web::Data<AppState>>().cloned() else {
+        return Err((actix_web::error::ErrorInternalServerError("app state is missing"), req));
+    };
+    // 服务账号使用 API key，可以放在 X-API-Key 或 Bearer 中
+    let api_key = req.headers().get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+        .or_else(|| credentials.as_ref()
+            .map(|c| c.token().to_string())
+            .filter(|t| ApiKeyService::is_api_key(t)));
+    if let Some(api_key) = api_key {
+        let ip = client_ip(req.request());
+        let claims = match ApiKeyService::authenticate(state, &api_key, ip.as_deref()).await {
+            Ok(claims) => claims,
+            Err(e) => return Err((e.into(), req)),
+        };
+        req.extensions_mut().insert(claims);
         return Ok(req);
     }
-    let Some(credentials) = credentials else {
-        return Err((actix_web::error::ErrorBadRequest("no bearer header"), req));
+    // 优先使用 Bearer 头，cookie 认证时非安全方法需要校验 CSRF token
+    let token = match credentials {
+        Some(credentials) => credentials.token().to_string(),
+        None => match auth_cookie::cookie_value(req.request(), auth_cookie::ACCESS_COOKIE) {
+            Some(token) => {
+                if let Err(e) = auth_cookie::check_csrf(req.request()) {
+                    return Err((e.into(), req));
+                }
+                token
+            }
+            None => return Err((actix_web::error::ErrorBadRequest("no bearer header"), req)),
+        },
     };
-    let token = credentials.token();
+    let token = token.as_str();
     info!("{:?}",token);
-    match Security::decode_token(token) {
-        Ok(_) => Ok(req),
-        Err(_) => Err((actix_web::error::ErrorUnauthorized("Unauthorized"), req))
+    let claims = match Security::decode_token(token) {
+        Ok(claims) => claims,
+        Err(_) => return Err((actix_web::error::ErrorUnauthorized("Unauthorized"), req))
+    };
+    if let Err(e) = Auth::check_token(state, &claims).await {
+        return Err((e.into(), req));
     }
+    req.extensions_mut().insert(claims);
+    Ok(req)
 }
 
 #[get("/query")]
@@ -183,11 +311,6 @@ async fn hello() -> impl Responder {
     HttpResponse::Ok().body("Hello world!")
 }
 
-async fn root_handler() -> &'static str {
-    warn!("处理根请求");
-    "Hello, Axum!"
-}
-
 #[derive(Deserialize)]
 struct DemoPage {
     page:usize,
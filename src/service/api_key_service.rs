@@ -0,0 +1,238 @@
+use std::net::IpAddr;
+use actix_web::web::Data;
+use chrono::{Duration, NaiveDateTime};
+use rand::distributions::{Alphanumeric, DistString};
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, EntityTrait, NotSet, Order, QueryFilter};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono::Local;
+use serde::{Deserialize, Serialize};
+use crate::{AppState, UserError};
+use crate::common::pagination::{paginate, Sorting};
+use crate::common::result::{FilterParam, PageResult};
+use crate::common::auth_user::AuthUser;
+use crate::common::client_ip::{contains, parse_net};
+use crate::common::security::{Claims, Security};
+use crate::common::soft_delete::SoftDelete;
+use crate::entity::prelude::{SysApiKey, User};
+use crate::entity::sys_api_key::{ActiveModel, Column, Model};
+use crate::service::user_service::UserService;
+
+/// API key 格式为 `ak_<prefix>_<secret>`，prefix 明文保存用于查找，整个 key 只保存 sha256 摘要
+pub const API_KEY_PREFIX: &str = "ak_";
+const PREFIX_LEN: usize = 8;
+/// 两次记录 `last_used_at` 的最小间隔，避免每次请求都写库
+const LAST_USED_INTERVAL_SECS: i64 = 60;
+
+pub struct ApiKeyService;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKey {
+    pub user_id: i32,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub ip_allow_list: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchApiKey {
+    pub user_id: Option<i32>,
+    pub name: Option<String>,
+}
+
+/// 创建时返回完整的 key，之后无法再查看
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyCreated {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: Model,
+}
+
+/// `ip` 是否在允许列表中，列表为空不限制
+fn ip_allowed(allow_list: &[String], ip: Option<&str>) -> bool {
+    if allow_list.is_empty() {
+        return true;
+    }
+    let Some(ip) = ip.and_then(|ip| ip.parse::<IpAddr>().ok()) else {
+        return false;
+    };
+    let nets: Vec<(IpAddr, u32)> = allow_list.iter().filter_map(|item| parse_net(item)).collect();
+    contains(&nets, ip)
+}
+
+/// key 的权限范围既不能超过服务账号的权限，也不能超过签发人自己的权限
+fn check_scopes(scopes: &[String], account_perms: &[String], caller_perms: &[String]) -> Result<(), UserError> {
+    if let Some(scope) = scopes.iter().find(|s| !account_perms.contains(s)) {
+        return Err(UserError::Error(format!("service account has no permission {}", scope)));
+    }
+    if let Some(scope) = scopes.iter().find(|s| !caller_perms.contains(s)) {
+        return Err(UserError::Forbidden(format!("permission denied: {}", scope)));
+    }
+    Ok(())
+}
+
+fn json_strings(value: &serde_json::Value) -> Vec<String> {
+    serde_json::from_value(value.clone()).unwrap_or_default()
+}
+
+const SORTING: Sorting<Column> = Sorting {
+    columns: &[
+        ("id", Column::Id),
+        ("name", Column::Name),
+        ("expiresAt", Column::ExpiresAt),
+        ("lastUsedAt", Column::LastUsedAt),
+        ("createdAt", Column::CreatedAt),
+    ],
+    default: &[(Column::Id, Order::Desc)],
+};
+
+impl ApiKeyService {
+
+    pub fn is_api_key(token: &str) -> bool {
+        token.starts_with(API_KEY_PREFIX)
+    }
+
+    /// 为服务账号签发 key，权限范围不能超过账号角色和签发人拥有的权限
+    pub async fn create(state: Data<AppState>, caller: &AuthUser, dto: CreateApiKey) -> Result<ApiKeyCreated, UserError> {
+        let Some(user) = User::active_by_id(dto.user_id).one(&state.conn).await? else {
+            return Err(UserError::ValidationError { field: "userId".to_string() });
+        };
+        if !user.service_account {
+            return Err(UserError::Error("api keys can only be issued to service accounts".to_string()));
+        }
+        let roles = UserService::find_role_ids(state.clone(), user.id).await?;
+        let perms = UserService::find_auth_code_by_roles(state.clone(), roles).await?;
+        check_scopes(&dto.scopes, &perms, &caller.perms)?;
+        if let Some(item) = dto.ip_allow_list.iter().find(|item| parse_net(item).is_none()) {
+            return Err(UserError::Error(format!("invalid ip allow list item {}", item)));
+        }
+        let prefix = Alphanumeric.sample_string(&mut rand::thread_rng(), PREFIX_LEN);
+        let key = format!("{}{}_{}", API_KEY_PREFIX, prefix, Security::generate_opaque_token());
+        let model = ActiveModel {
+            id: NotSet,
+            user_id: Set(user.id),
+            name: Set(dto.name),
+            prefix: Set(prefix),
+            key_hash: Set(Security::hash_token(&key)),
+            scopes: Set(serde_json::json!(dto.scopes)),
+            ip_allow_list: Set(if dto.ip_allow_list.is_empty() { None } else { Some(serde_json::json!(dto.ip_allow_list)) }),
+            expires_at: Set(dto.expires_at),
+            last_used_at: NotSet,
+            last_used_ip: NotSet,
+            revoked_at: NotSet,
+            created_by: Set(Some(caller.id)),
+            updated_at: NotSet,
+            created_at: Set(Local::now().naive_local()),
+        };
+        let api_key = model.insert(&state.conn).await?;
+        Ok(ApiKeyCreated { key, api_key })
+    }
+
+    pub async fn find_all(state: Data<AppState>, mut page: FilterParam<SearchApiKey>) -> Result<PageResult<Model>, UserError> {
+        let mut conditions = Condition::all();
+        if let Some(f) = page.filters.take() {
+            if let Some(user_id) = f.user_id {
+                conditions = conditions.add(Column::UserId.eq(user_id));
+            }
+            if let Some(name) = f.name {
+                conditions = conditions.add(Column::Name.contains(name));
+            }
+        }
+        paginate(&state.conn, SysApiKey::find().filter(conditions), &page, &SORTING).await
+    }
+
+    pub async fn revoke(state: Data<AppState>, id: i32) -> Result<(), UserError> {
+        let now = Local::now().naive_local();
+        let result = SysApiKey::update_many()
+            .col_expr(Column::RevokedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(id))
+            .filter(Column::RevokedAt.is_null())
+            .exec(&state.conn)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(UserError::DbErr(sea_orm::DbErr::RecordNotFound(id.to_string())));
+        }
+        Ok(())
+    }
+
+    /// 校验 key 并构造调用方的 claims，权限为 key 的范围与账号当前权限的交集
+    pub async fn authenticate(state: Data<AppState>, key: &str, ip: Option<&str>) -> Result<Claims, UserError> {
+        let invalid = || UserError::Unauthorized("invalid api key".to_string());
+        let prefix = key.strip_prefix(API_KEY_PREFIX)
+            .and_then(|rest| rest.split_once('_'))
+            .map(|(prefix, _)| prefix)
+            .ok_or_else(invalid)?;
+        let Some(api_key) = SysApiKey::find()
+            .filter(Column::Prefix.eq(prefix))
+            .one(&state.conn)
+            .await? else {
+            return Err(invalid());
+        };
+        let now = Local::now().naive_local();
+        if api_key.key_hash != Security::hash_token(key)
+            || api_key.revoked_at.is_some()
+            || api_key.expires_at.map(|t| t <= now).unwrap_or(false) {
+            return Err(invalid());
+        }
+        let allow_list = api_key.ip_allow_list.as_ref().map(json_strings).unwrap_or_default();
+        if !ip_allowed(&allow_list, ip) {
+            return Err(UserError::Forbidden("ip is not allowed for this api key".to_string()));
+        }
+        let Some(user) = User::active_by_id(api_key.user_id).one(&state.conn).await? else {
+            return Err(invalid());
+        };
+        if !user.available {
+            return Err(UserError::AccountDisabled);
+        }
+        let roles = UserService::find_role_ids(state.clone(), user.id).await?;
+        let perms = UserService::find_auth_code_by_roles(state.clone(), roles.clone()).await?;
+        let scopes = json_strings(&api_key.scopes);
+        let perms = perms.into_iter().filter(|p| scopes.contains(p)).collect();
+        if api_key.last_used_at.map(|t| now - t > Duration::seconds(LAST_USED_INTERVAL_SECS)).unwrap_or(true) {
+            SysApiKey::update_many()
+                .col_expr(Column::LastUsedAt, Expr::value(now))
+                .col_expr(Column::LastUsedIp, Expr::value(ip.map(|s| s.to_string())))
+                .filter(Column::Id.eq(api_key.id))
+                .exec(&state.conn)
+                .await?;
+        }
+        Ok(Claims::for_api_key(user.id, user.user_name, roles, perms, api_key.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::client_ip::parse_net;
+    use crate::service::api_key_service::{check_scopes, ip_allowed};
+
+    #[test]
+    fn test_ip_allowed() {
+        let list = vec!["10.0.0.0/8".to_string(), "192.168.1.10".to_string(), "fd00::/8".to_string()];
+        assert!(ip_allowed(&[], None));
+        assert!(ip_allowed(&list, Some("10.1.2.3")));
+        assert!(ip_allowed(&list, Some("192.168.1.10")));
+        assert!(!ip_allowed(&list, Some("192.168.1.11")));
+        assert!(ip_allowed(&list, Some("fd12::1")));
+        assert!(!ip_allowed(&list, Some("fe80::1")));
+        assert!(!ip_allowed(&list, None));
+        assert!(ip_allowed(&["0.0.0.0/0".to_string()], Some("8.8.8.8")));
+        assert!(parse_net("10.0.0.0/33").is_none());
+    }
+
+    #[test]
+    fn test_check_scopes() {
+        let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let account = strings(&["a", "b", "c"]);
+        assert!(check_scopes(&strings(&["a", "b"]), &account, &strings(&["a", "b"])).is_ok());
+        // 签发人的权限比服务账号少
+        let err = check_scopes(&strings(&["a", "c"]), &account, &strings(&["a", "b"])).unwrap_err();
+        assert_eq!(err.to_string(), "permission denied: c");
+        assert!(check_scopes(&strings(&["d"]), &account, &strings(&["d"])).is_err());
+    }
+}
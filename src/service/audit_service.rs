@@ -0,0 +1,67 @@
+use actix_web::web::Data;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, NotSet, Order, QueryFilter};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sqlx::types::chrono::Local;
+use serde::{Deserialize, Serialize};
+use crate::{AppState, UserError};
+use crate::common::pagination::{paginate, Sorting};
+use crate::common::result::{FilterParam, PageResult};
+use crate::entity::prelude::SysAuditLog;
+use crate::entity::sys_audit_log::{ActiveModel, Column, Model};
+
+pub const IMPERSONATION_START: &str = "impersonation.start";
+pub const IMPERSONATION_STOP: &str = "impersonation.stop";
+
+/// 敏感操作的审计日志
+pub struct AuditService;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchAuditLog {
+    pub actor_id: Option<i32>,
+    pub target_id: Option<i32>,
+    pub action: Option<String>,
+}
+
+const SORTING: Sorting<Column> = Sorting {
+    columns: &[
+        ("id", Column::Id),
+        ("action", Column::Action),
+        ("createdAt", Column::CreatedAt),
+    ],
+    default: &[(Column::Id, Order::Desc)],
+};
+
+impl AuditService {
+
+    pub async fn record<C>(conn: &C, actor_id: i32, action: &str, target_id: Option<i32>, detail: Option<String>, ip: Option<String>) -> Result<(), UserError>
+    where C: ConnectionTrait {
+        let model = ActiveModel {
+            id: NotSet,
+            actor_id: Set(actor_id),
+            action: Set(action.to_string()),
+            target_id: Set(target_id),
+            detail: Set(detail),
+            ip: Set(ip),
+            created_at: Set(Local::now().naive_local()),
+        };
+        model.insert(conn).await?;
+        Ok(())
+    }
+
+    pub async fn find_all(state: Data<AppState>, mut page: FilterParam<SearchAuditLog>) -> Result<PageResult<Model>, UserError> {
+        let mut conditions = Condition::all();
+        if let Some(f) = page.filters.take() {
+            if let Some(actor_id) = f.actor_id {
+                conditions = conditions.add(Column::ActorId.eq(actor_id));
+            }
+            if let Some(target_id) = f.target_id {
+                conditions = conditions.add(Column::TargetId.eq(target_id));
+            }
+            if let Some(action) = f.action {
+                conditions = conditions.add(Column::Action.eq(action));
+            }
+        }
+        paginate(&state.conn, SysAuditLog::find().filter(conditions), &page, &SORTING).await
+    }
+}
@@ -1,56 +1,335 @@
-use std::io::Error;
-use crate::common::security::{Claims, Security};
-use crate::service::user_service::UserService;
+use crate::common::security::{Claims, Security, ACCESS_TOKEN_EXPIRES_SECS};
+use crate::common::auth_user::AuthUser;
+use crate::common::config::{MFA_TOKEN_EXPIRES_SECS, PASSWORD_MAX_AGE_DAYS};
+use crate::entity::user;
+use crate::service::auth_provider::LOCAL_PROVIDER;
+use crate::service::login_attempt_service::LoginAttemptService;
+use crate::service::menu_service::{MenuNode, MenuService};
+use crate::service::mfa_service::{MfaEnrollment, MfaService};
+use crate::service::refresh_token_service::RefreshTokenService;
+use crate::service::session_service::{ClientInfo, SessionService};
+use crate::service::token_revocation_service::TokenRevocationService;
+use crate::service::user_service::{ModifyPassword, UserName, UserService};
 use crate::{AppState, UserError};
 use actix_web::web::Data;
-use jsonwebtoken::{EncodingKey, Header};
-use log::info;
+use chrono::{Duration, NaiveDateTime};
+use log::{info, warn};
+use sea_orm::DbErr;
+use sea_orm::sqlx::types::chrono::Local;
 use serde::{Deserialize, Serialize};
-use crate::common::simple_cache::Cache;
 
 pub struct Auth;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+/// 需要两步验证时登录第一步的返回
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaChallenge {
+    pub mfa_token: String,
+    pub expires_in: i64,
+    /// 角色要求两步验证但尚未绑定时返回，用验证码确认绑定
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enrollment: Option<MfaEnrollment>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum SignInResult {
+    Token(TokenPair),
+    MfaRequired(MfaChallenge),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaSignIn {
+    #[serde(flatten)]
+    pub token: TokenPair,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
+}
+
+/// 当前用户的资料、角色、权限码和菜单
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Me {
+    pub user: user::Model,
+    pub role_ids: Vec<i32>,
+    pub perm_codes: Vec<String>,
+    pub menus: Vec<MenuNode>,
+}
+
+/// 本地密码是否已超过有效期；外部目录的密码不在这里管理
+fn password_expired(user: &UserName, now: NaiveDateTime, max_age_days: i64) -> bool {
+    let local = user.auth_provider.as_deref().is_none_or(|p| p == LOCAL_PROVIDER);
+    local && max_age_days > 0 && now - user.password_changed_at > Duration::days(max_age_days)
+}
+
 impl Auth {
-    pub async fn sign_in(state:Data<AppState>, username:String, password:String) -> Result<String, UserError> {
-        if Cache::get_cache(username.clone()).is_some() {
-            let token = Cache::get_cache(username).unwrap();
-            return Ok(token);
+    /// 按用户的认证方式校验用户名密码，连续失败会锁定账号
+    async fn authenticate(state:Data<AppState>, username:&str, password:&str, ip:Option<&str>) -> Result<UserName, UserError> {
+        let store = state.login_attempts.clone();
+        LoginAttemptService::check(store.as_ref(), username, ip).await?;
+        let user = Self::find_user(state.clone(), username).await?;
+        // 用户不存在和密码错误返回相同的错误，避免枚举用户名；服务账号不能用密码登录
+        let mut user_id = None;
+        let mut error = None;
+        if !user.as_ref().map(|u| u.service_account).unwrap_or(false) {
+            let pinned = user.as_ref().and_then(|u| u.auth_provider.as_deref());
+            for provider in state.auth_providers.for_user(pinned) {
+                match provider.authenticate(state.clone(), user.as_ref(), username, password).await {
+                    Ok(Some(id)) => {
+                        user_id = Some(id);
+                        break;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("auth provider {} error: {}", provider.name(), e);
+                        error = Some(e);
+                    }
+                }
+            }
+        }
+        if user_id.is_none() {
+            // 认证服务不可用时不计入失败次数
+            if let Some(e) = error {
+                return Err(e);
+            }
+            LoginAttemptService::record_failure(store.as_ref(), username, ip).await?;
+            return Err(UserError::BadCredentials);
+        }
+        LoginAttemptService::reset(store.as_ref(), username).await?;
+        // 认证方式可能创建或更新了本地用户，重新查询
+        let Some(user) = Self::find_user(state.clone(), username).await? else {
+            return Err(UserError::BadCredentials);
+        };
+        if !user.available {
+            return Err(UserError::AccountDisabled);
+        }
+        Ok(user)
+    }
+
+    async fn find_user(state:Data<AppState>, username:&str) -> Result<Option<UserName>, UserError> {
+        match UserService::find_one_by_user_name(state, username.to_string()).await {
+            Ok(user) => Ok(Some(user)),
+            Err(DbErr::RecordNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
         }
+    }
+
+    /// 每次登录都校验密码并创建新的会话，同一用户可在多台设备同时登录。
+    /// 启用了两步验证或角色要求两步验证时，只返回待验证 token
+    pub async fn sign_in(state:Data<AppState>, username:String, password:String, client:ClientInfo) -> Result<SignInResult, UserError> {
         info!("Username: {}", username);
-        let user = UserService::find_one_by_user_name(state, username.clone()).await?;
-        let verify = Security::verify(
-            user.password.as_str(),
-            password.as_str()
-        );
-        if !verify {
-            return Err(UserError::Error("password not match".to_string()));
+        let ip = client.ip.clone();
+        let user = Self::authenticate(state.clone(), &username, &password, ip.as_deref()).await?;
+        // 密码过期只能通过 /auth/password/expired 修改后再登录
+        if password_expired(&user, Local::now().naive_local(), *PASSWORD_MAX_AGE_DAYS) {
+            return Err(UserError::PasswordExpired);
+        }
+        if let Some(challenge) = Self::mfa_challenge(state.clone(), user.id, &username).await? {
+            return Ok(SignInResult::MfaRequired(challenge));
+        }
+        let token = Self::create_session(state, user.id, username, client).await?;
+        Ok(SignInResult::Token(token))
+    }
+
+    /// 已启用两步验证或角色要求时返回第二步的 token，密码登录和外部登录共用
+    async fn mfa_challenge(state:Data<AppState>, user_id:i32, user_name:&str) -> Result<Option<MfaChallenge>, UserError> {
+        let enabled = MfaService::find(&state.conn, user_id).await?
+            .map(|m| m.enabled_at.is_some())
+            .unwrap_or(false);
+        if !enabled && !MfaService::is_required(state.clone(), user_id).await? {
+            return Ok(None);
+        }
+        // 角色要求但尚未绑定的，登录时完成绑定
+        let enrollment = if enabled {
+            None
+        } else {
+            Some(MfaService::pending_enrollment(state.clone(), user_id, user_name).await?)
+        };
+        Ok(Some(MfaChallenge {
+            mfa_token: Security::encode_mfa_token(user_id)?,
+            expires_in: *MFA_TOKEN_EXPIRES_SECS,
+            enrollment,
+        }))
+    }
+
+    /// 登录第二步：校验验证码（或恢复码）后创建会话；首次绑定时同时返回恢复码
+    pub async fn verify_mfa(state:Data<AppState>, mfa_token:String, code:String, client:ClientInfo) -> Result<MfaSignIn, UserError> {
+        let user_id = Security::decode_mfa_token(&mfa_token)?;
+        let Some(user) = UserService::find_one(state.clone(), user_id).await?.result else {
+            return Err(UserError::Unauthorized("user not found".to_string()));
+        };
+        if !user.available {
+            return Err(UserError::AccountDisabled);
         }
+        let store = state.login_attempts.clone();
+        let ip = client.ip.clone();
+        LoginAttemptService::check(store.as_ref(), &user.user_name, ip.as_deref()).await?;
+        let Some(mfa) = MfaService::find(&state.conn, user.id).await? else {
+            return Err(UserError::Unauthorized("mfa is not enrolled".to_string()));
+        };
+        let result = if mfa.enabled_at.is_some() {
+            MfaService::verify(state.clone(), &mfa, &code).await.map(|_| None)
+        } else {
+            MfaService::confirm(state.clone(), user.id, &code).await.map(Some)
+        };
+        let recovery_codes = match result {
+            Ok(codes) => codes,
+            Err(UserError::InvalidMfaCode) => {
+                LoginAttemptService::record_failure(store.as_ref(), &user.user_name, ip.as_deref()).await?;
+                return Err(UserError::InvalidMfaCode);
+            }
+            Err(e) => return Err(e),
+        };
+        LoginAttemptService::reset(store.as_ref(), &user.user_name).await?;
+        let token = Self::create_session(state, user.id, user.user_name, client).await?;
+        Ok(MfaSignIn { token, recovery_codes })
+    }
+
+    /// 外部身份提供方登录，不信任身份提供方的认证强度，两步验证的要求与密码登录相同
+    pub async fn sign_in_external(state:Data<AppState>, user:user::Model, client:ClientInfo) -> Result<SignInResult, UserError> {
+        info!("External sign in: {}", user.user_name);
+        if user.service_account {
+            return Err(UserError::Forbidden("service accounts can not sign in".to_string()));
+        }
+        if !user.available {
+            return Err(UserError::AccountDisabled);
+        }
+        if let Some(challenge) = Self::mfa_challenge(state.clone(), user.id, &user.user_name).await? {
+            return Ok(SignInResult::MfaRequired(challenge));
+        }
+        let token = Self::create_session(state, user.id, user.user_name, client).await?;
+        Ok(SignInResult::Token(token))
+    }
+
+    async fn create_session(state:Data<AppState>, user_id:i32, user_name:String, client:ClientInfo) -> Result<TokenPair, UserError> {
+        UserService::record_login(state.clone(), user_id, client.ip.clone()).await?;
+        let session = SessionService::create(state.clone(), user_id, client).await?;
+        let access_token = Self::issue_access_token(state.clone(), user_id, user_name, session.id.clone()).await?;
+        let refresh_token = RefreshTokenService::issue(&state.conn, user_id, session.id).await?;
+        Ok(TokenPair {
+            access_token,
+            refresh_token,
+            expires_in: ACCESS_TOKEN_EXPIRES_SECS,
+        })
+    }
+
+    /// 密码过期时用旧密码换新密码，不需要登录
+    pub async fn change_expired_password(state:Data<AppState>, username:String, pwd:ModifyPassword, ip:Option<String>) -> Result<(), UserError> {
+        let user = Self::authenticate(state.clone(), &username, &pwd.old_password, ip.as_deref()).await?;
+        // 不需要登录，只允许本地用户修改确已过期的密码，其他情况与密码错误的响应相同
+        if !password_expired(&user, Local::now().naive_local(), *PASSWORD_MAX_AGE_DAYS) {
+            return Err(UserError::BadCredentials);
+        }
+        let Some(model) = UserService::find_one(state.clone(), user.id).await?.result else {
+            return Err(UserError::BadCredentials);
+        };
+        UserService::set_password(&state.conn, &model, &pwd.new_password).await
+    }
 
-        match Security::encode_token(user.id,username.clone()) {
-            Ok(token) => {
-                Cache::set_cache(username.clone(),token.clone());
-                Ok(token)
-            },
-            Err(_) => Err(UserError::Error("error encoding token".to_string()))
+    pub async fn refresh(state:Data<AppState>, refresh_token:String) -> Result<TokenPair, UserError> {
+        let (used, refresh_token) = RefreshTokenService::rotate(state.clone(), refresh_token).await?;
+        let Some(session) = SessionService::find_active(state.clone(), &used.family_id).await? else {
+            return Err(UserError::Unauthorized("session is expired".to_string()));
+        };
+        SessionService::touch(state.clone(), &session.id).await?;
+        let Some(user) = UserService::find_one(state.clone(), used.user_id).await?.result else {
+            return Err(UserError::Unauthorized("user not found".to_string()));
+        };
+        if !user.available {
+            return Err(UserError::AccountDisabled);
         }
+        let access_token = Self::issue_access_token(state, user.id, user.user_name, session.id).await?;
+        Ok(TokenPair {
+            access_token,
+            refresh_token,
+            expires_in: ACCESS_TOKEN_EXPIRES_SECS,
+        })
+    }
+
+    /// access token 携带用户的角色 id 和权限码
+    async fn issue_access_token(state:Data<AppState>, user_id:i32, user_name:String, session_id:String) -> Result<String, UserError> {
+        let roles = UserService::find_role_ids(state.clone(), user_id).await?;
+        let perms = UserService::find_auth_code_by_roles(state, roles.clone()).await?;
+        Security::encode_token(user_id, user_name, session_id, roles, perms)
+    }
 
+    /// 退出当前会话：吊销当前 access token 以及该会话的 refresh token
+    pub async fn sign_out(state:Data<AppState>, claims:Claims) -> Result<(), UserError> {
+        TokenRevocationService::revoke(state.clone(), &claims).await?;
+        // 模拟登录沿用操作人的会话，只吊销 token
+        if claims.act.is_none() {
+            SessionService::revoke_with(&state.conn, claims.sid).await?;
+        }
+        Ok(())
     }
 
-    pub async fn sign_out(token:String) -> Result<String, UserError> {
-        let t:Vec<&str> = token.split_whitespace().collect();
-        let real = if let Some(s) = t.get(1) {
-            s.to_string()
-        }else {
-            return Err(UserError::Error("token is fail".to_string()))
+    pub async fn me(state:Data<AppState>, user:AuthUser) -> Result<Me, UserError> {
+        let Some(model) = UserService::find_one(state.clone(), user.id).await?.result else {
+            return Err(UserError::Unauthorized("user not found".to_string()));
         };
-        if real.is_empty() {
-            return Err(UserError::Error("token is empty".to_string()));
+        let menus = MenuService::get_menu_tree_by_user_auth_code(state, user.perms.clone()).await?;
+        Ok(Me {
+            user: model,
+            role_ids: user.roles,
+            perm_codes: user.perms,
+            menus,
+        })
+    }
+
+    /// 校验 token 是否已被吊销、其所属会话是否已失效、用户是否已被禁用
+    pub async fn check_token(state:Data<AppState>, claims:&Claims) -> Result<(), UserError> {
+        if TokenRevocationService::is_revoked(state.clone(), &claims.jti).await? {
+            return Err(UserError::Unauthorized("token is revoked".to_string()));
         }
-        let claims = Security::decode_token(real.as_str())?;
-        if let Some(user_name) =  Cache::remove_cache(claims.user_name) {
-            Ok(user_name)
-        }else {
-            Err(UserError::Error("error".to_string()))
+        if SessionService::find_active(state.clone(), &claims.sid).await?.is_none() {
+            return Err(UserError::Unauthorized("session is revoked".to_string()));
         }
+        // 签发后被禁用的用户，模拟登录时操作人也需要启用
+        if !UserService::is_available(state.clone(), claims.user_id()?).await? {
+            return Err(UserError::AccountDisabled);
+        }
+        if let Some(actor_id) = claims.actor_id()? {
+            if !UserService::is_available(state, actor_id).await? {
+                return Err(UserError::AccountDisabled);
+            }
+        }
+        Ok(())
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDateTime};
+    use crate::service::auth::password_expired;
+    use crate::service::user_service::UserName;
+
+    #[test]
+    fn test_password_expired() {
+        let changed_at = NaiveDateTime::default();
+        let user = |auth_provider: Option<&str>| UserName {
+            id: 1,
+            password: String::new(),
+            available: true,
+            service_account: false,
+            auth_provider: auth_provider.map(str::to_string),
+            password_changed_at: changed_at,
+        };
+        let later = changed_at + Duration::days(91);
+        assert!(password_expired(&user(None), later, 90));
+        assert!(password_expired(&user(Some("local")), later, 90));
+        // 未过期、不限制有效期或外部目录的用户都不能走过期改密
+        assert!(!password_expired(&user(None), changed_at + Duration::days(30), 90));
+        assert!(!password_expired(&user(None), later, 0));
+        assert!(!password_expired(&user(Some("ldap")), later, 90));
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use actix_web::web::Data;
+use async_trait::async_trait;
+use log::{error, warn};
+use crate::{AppState, UserError};
+use crate::common::config::AUTH_PROVIDER_CHAIN;
+use crate::common::security::Security;
+use crate::service::ldap_auth_provider::{LdapAuthProvider, LdapConfig};
+use crate::service::user_service::{UserName, UserService};
+
+pub const LOCAL_PROVIDER: &str = "local";
+pub const LDAP_PROVIDER: &str = "ldap";
+
+/// 用户名密码的认证方式
+#[async_trait]
+pub trait AuthProvider: Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 认证成功返回本地用户 id（需要时由认证方式创建或同步本地用户），
+    /// 不认识该用户或密码错误返回 None，交给下一个认证方式
+    async fn authenticate(&self, state: Data<AppState>, user: Option<&UserName>, username: &str, password: &str) -> Result<Option<i32>, UserError>;
+}
+
+/// 本地 `user` 表中的密码哈希
+#[derive(Debug, Default)]
+pub struct LocalAuthProvider;
+
+#[async_trait]
+impl AuthProvider for LocalAuthProvider {
+    fn name(&self) -> &'static str {
+        LOCAL_PROVIDER
+    }
+
+    async fn authenticate(&self, state: Data<AppState>, user: Option<&UserName>, _username: &str, password: &str) -> Result<Option<i32>, UserError> {
+        let Some(user) = user.filter(|u| Security::verify(u.password.as_str(), password)) else {
+            return Ok(None);
+        };
+        if Security::needs_rehash(&user.password) {
+            // 升级失败不影响本次登录
+            if let Err(e) = UserService::upgrade_password_hash(state, user.id, password).await {
+                warn!("upgrade password hash of user {} error: {}", user.id, e);
+            }
+        }
+        Ok(Some(user.id))
+    }
+}
+
+/// 已启用的认证方式和默认的尝试顺序
+#[derive(Debug)]
+pub struct AuthProviders {
+    providers: Vec<Arc<dyn AuthProvider>>,
+    chain: Vec<Arc<dyn AuthProvider>>,
+}
+
+impl AuthProviders {
+
+    pub fn new(providers: Vec<Arc<dyn AuthProvider>>, chain: &str) -> Self {
+        let mut list = Vec::new();
+        for name in chain.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match providers.iter().find(|p| p.name() == name) {
+                Some(provider) => list.push(provider.clone()),
+                None => warn!("auth provider {} is not enabled, skipped", name),
+            }
+        }
+        AuthProviders { providers, chain: list }
+    }
+
+    pub fn find(&self, name: &str) -> Option<Arc<dyn AuthProvider>> {
+        self.providers.iter().find(|p| p.name() == name).cloned()
+    }
+
+    /// 用户指定了认证方式时只使用该方式，否则按顺序尝试
+    pub fn for_user(&self, pinned: Option<&str>) -> Vec<Arc<dyn AuthProvider>> {
+        match pinned {
+            Some(name) => self.find(name).into_iter().collect(),
+            None => self.chain.clone(),
+        }
+    }
+}
+
+pub fn create_auth_providers() -> Arc<AuthProviders> {
+    let mut providers: Vec<Arc<dyn AuthProvider>> = vec![Arc::new(LocalAuthProvider)];
+    match LdapConfig::from_env() {
+        Ok(Some(config)) => providers.push(Arc::new(LdapAuthProvider::new(config))),
+        Ok(None) => {}
+        Err(e) => error!("ldap config error: {}, ldap is disabled", e),
+    }
+    Arc::new(AuthProviders::new(providers, &AUTH_PROVIDER_CHAIN))
+}
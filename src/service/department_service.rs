@@ -1,14 +1,18 @@
 use actix_web::web::{Data, Json, Path};
-use log::info;
-use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, DbErr, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QueryTrait};
-use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, NotSet, Order, PaginatorTrait, QueryFilter, Select, TransactionTrait, Value};
+use sea_orm::ActiveValue::{Set, Unchanged};
 use sea_orm::prelude::DateTime;
-use sea_orm::sqlx::types::chrono::{Local, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono::Local;
 use serde::{Deserialize, Serialize};
 use crate::{AppState, UserError};
+use crate::common::pagination::{paginate, Sorting};
 use crate::common::result::{FilterParam, PageResult};
+use crate::common::soft_delete::SoftDelete;
+use crate::common::tree::{build_tree, TreeItem, TreeNode};
 use crate::entity::department::{ActiveModel, Column, Model};
-use crate::entity::prelude::{Department};
+use crate::entity::prelude::{Department, User};
+use crate::entity::user;
 
 pub struct DepartmentService{}
 
@@ -31,6 +35,13 @@ pub struct UpdateDepartment {
     pub create_department: CreateDepartment,
 }
 
+#[derive(Deserialize,Serialize,Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveDepartment {
+    /// 为空或 0 时移动为根部门
+    pub father_id: Option<i32>,
+}
+
 #[derive(Deserialize,Serialize,Debug)]
 pub struct DelParams {
     pub ids:Vec<i32>
@@ -42,41 +53,201 @@ pub struct SearchParams {
     pub department_name:Option<String>,
 }
 
+const SORTING: Sorting<Column> = Sorting {
+    columns: &[
+        ("id", Column::Id),
+        ("orderNum", Column::OrderNum),
+        ("departmentName", Column::DepartmentName),
+        ("createdAt", Column::CreatedAt),
+    ],
+    default: &[(Column::OrderNum, Order::Asc), (Column::Id, Order::Asc)],
+};
+
+pub type DepartmentNode = TreeNode<Model>;
+
+impl TreeItem for Model {
+    fn id(&self) -> i32 {
+        self.id
+    }
+    fn father_id(&self) -> i32 {
+        self.father_id.unwrap_or(0)
+    }
+    fn order_num(&self) -> i32 {
+        self.order_num.unwrap_or(0)
+    }
+}
+
 impl DepartmentService {
     pub async fn create(state:Data<AppState>, Json(create_params):Json<CreateDepartment>) ->Result<Model,UserError> {
+        let txn = state.conn.begin().await?;
+        let father = Self::find_father(&txn, create_params.father_id).await?;
         let active_model = ActiveModel {
             id: NotSet,
-            father_id: Set(create_params.father_id),
+            father_id: Set(Some(father.as_ref().map_or(0, |f| f.id))),
             department_name: Set(create_params.department_name.to_owned()),
             order_num: Set(create_params.order_num),
             state: Set(Some(create_params.state.unwrap_or(false))),
+            path: Set(String::new()),
             updated_at: NotSet,
             created_at: Set(Local::now().naive_local()),
             deleted_at: NotSet,
         };
-        let result = active_model.insert(&state.conn).await?;
+        let result = active_model.insert(&txn).await?;
+        // 路径包含自身的 id，插入后才能确定
+        let mut active_model: ActiveModel = result.into();
+        active_model.path = Set(Self::path_of(father.as_ref(), *active_model.id.as_ref()));
+        let result = active_model.update(&txn).await?;
+        txn.commit().await?;
         Ok(result)
     }
 
+    /// 移入回收站，仍有下级部门或用户的部门不能删除
     pub async fn delete(state:Data<AppState>, del_params:DelParams) ->Result<u64,UserError> {
-        let x = Department::delete_many()
+        let children = Department::active()
+            .filter(Column::FatherId.is_in(del_params.ids.clone()))
+            .filter(Column::Id.is_not_in(del_params.ids.clone()))
+            .count(&state.conn)
+            .await?;
+        if children > 0 {
+            return Err(UserError::Conflict("department still has child departments".to_string()));
+        }
+        let users = User::active()
+            .filter(user::Column::DepartmentId.is_in(del_params.ids.clone()))
+            .count(&state.conn)
+            .await?;
+        if users > 0 {
+            return Err(UserError::Conflict("department still has users".to_string()));
+        }
+        let x = Department::soft_delete(Local::now().naive_local())
             .filter(Column::Id.is_in(del_params.ids))
             .exec(&state.conn)
             .await?;
         Ok(x.rows_affected)
     }
 
+    pub async fn restore(state:Data<AppState>, del_params:DelParams) ->Result<u64,UserError> {
+        let x = Department::restore()
+            .filter(Column::Id.is_in(del_params.ids))
+            .exec(&state.conn)
+            .await?;
+        Ok(x.rows_affected)
+    }
+
+    /// 彻底删除回收站中的部门
+    pub async fn purge(state:Data<AppState>, del_params:DelParams) ->Result<u64,UserError> {
+        let x = Department::purge()
+            .filter(Column::Id.is_in(del_params.ids))
+            .exec(&state.conn)
+            .await?;
+        Ok(x.rows_affected)
+    }
+
+    /// 修改上级时与 `move_to` 一样检查是否成环
     pub async fn update(state:Data<AppState>, Json(update_params):Json<UpdateDepartment>) ->Result<Model,UserError> {
-        let value = serde_json::to_value(&update_params)?;
-        let mut result = ActiveModel::from_json(value)?;
-        result.created_at = NotSet;
-        let model = result.update(&state.conn).await?;
+        let txn = state.conn.begin().await?;
+        let current = Self::find_active(&txn, update_params.id).await?;
+        let create = update_params.create_department;
+        if create.father_id.unwrap_or(0) != current.father_id.unwrap_or(0) {
+            Self::move_with(&txn, &current, create.father_id).await?;
+        }
+        let active_model = ActiveModel {
+            id: Unchanged(current.id),
+            department_name: Set(create.department_name),
+            order_num: Set(create.order_num),
+            state: Set(create.state),
+            updated_at: Set(Some(Local::now().naive_local())),
+            ..Default::default()
+        };
+        let model = Department::update(active_model)
+            .filter(Column::DeletedAt.is_null())
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(model)
+    }
+
+    /// 移动到新的上级下，下级部门随之移动
+    pub async fn move_to(state:Data<AppState>, id:i32, params:MoveDepartment) ->Result<Model,UserError> {
+        let txn = state.conn.begin().await?;
+        let current = Self::find_active(&txn, id).await?;
+        Self::move_with(&txn, &current, params.father_id).await?;
+        let model = Self::find_active(&txn, id).await?;
+        txn.commit().await?;
         Ok(model)
     }
 
+    async fn move_with<C: ConnectionTrait>(db:&C, department:&Model, father_id:Option<i32>) ->Result<(),UserError> {
+        let father = Self::find_father(db, father_id).await?;
+        if let Some(father) = &father {
+            if Self::is_descendant(&father.path, &department.path) {
+                return Err(UserError::ValidationError { field: "fatherId".to_string() });
+            }
+        }
+        // 回收站中的下级也一起改写路径，恢复后仍在原位置
+        let path = Self::path_of(father.as_ref(), department.id);
+        Department::update_many()
+            .col_expr(Column::Path, Expr::cust_with_values(r#"$1 || substr("path", $2)"#, [
+                Value::from(path),
+                Value::from(department.path.len() as i32 + 1),
+            ]))
+            .filter(Column::Path.starts_with(&department.path))
+            .exec(db)
+            .await?;
+        Department::update_many()
+            .col_expr(Column::FatherId, Expr::value(father.as_ref().map_or(0, |f| f.id)))
+            .col_expr(Column::UpdatedAt, Expr::value(Local::now().naive_local()))
+            .filter(Column::Id.eq(department.id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// 上级为空或 0 时返回 `None`，上级不存在或已删除时报错
+    async fn find_father<C: ConnectionTrait>(db:&C, father_id:Option<i32>) ->Result<Option<Model>,UserError> {
+        match father_id {
+            None | Some(0) => Ok(None),
+            Some(id) => match Department::active_by_id(id).one(db).await? {
+                Some(father) => Ok(Some(father)),
+                None => Err(UserError::ValidationError { field: "fatherId".to_string() }),
+            },
+        }
+    }
+
+    async fn find_active<C: ConnectionTrait>(db:&C, id:i32) ->Result<Model,UserError> {
+        match Department::active_by_id(id).one(db).await? {
+            Some(model) => Ok(model),
+            None => Err(UserError::from(DbErr::RecordNotFound(id.to_string()))),
+        }
+    }
+
+    fn path_of(father:Option<&Model>, id:i32) -> String {
+        format!("{}{}/", father.map_or("/", |f| f.path.as_str()), id)
+    }
+
+    /// `path` 是 `ancestor` 自身或其下级的路径
+    fn is_descendant(path:&str, ancestor:&str) -> bool {
+        path.starts_with(ancestor)
+    }
+
+    /// 完整的部门树
+    pub async fn find_tree(state:Data<AppState>) ->Result<Vec<DepartmentNode>,UserError> {
+        let vec = Department::active().all(&state.conn).await?;
+        Ok(build_tree(vec, 0))
+    }
+
+    /// 指定部门及其全部下级
+    pub async fn find_subtree(state:Data<AppState>, id:i32) ->Result<Option<DepartmentNode>,UserError> {
+        let department = Self::find_active(&state.conn, id).await?;
+        let vec = Department::active()
+            .filter(Column::Path.starts_with(&department.path))
+            .all(&state.conn)
+            .await?;
+        Ok(build_tree(vec, TreeItem::father_id(&department)).pop())
+    }
+
     pub async fn find_one(state: Data<AppState>, id :Path<i32>) ->Result<Model,UserError> {
         let key = id.into_inner();
-        let option = Department::find_by_id(key).one(&state.conn).await?;
+        let option = Department::active_by_id(key).one(&state.conn).await?;
         if let Some(s) = option {
             Ok(s)
         }else {
@@ -84,16 +255,33 @@ impl DepartmentService {
         }
     }
 
-    pub async fn find_all(state:Data<AppState>, Json(list):Json<SearchParams>) -> Result<PageResult<Model>, DbErr> {
+    pub async fn find_all(state:Data<AppState>, Json(page):Json<FilterParam<SearchParams>>) -> Result<PageResult<Model>, UserError> {
+        Self::search(state, Department::active(), page).await
+    }
+
+    /// 回收站
+    pub async fn find_trash(state:Data<AppState>, page:FilterParam<SearchParams>) -> Result<PageResult<Model>, UserError> {
+        Self::search(state, Department::trashed(), page).await
+    }
+
+    async fn search(state:Data<AppState>, select:Select<Department>, mut page:FilterParam<SearchParams>) -> Result<PageResult<Model>, UserError> {
         let mut condition = Condition::all();
-        if let Some(department_name) = list.department_name {
+        if let Some(department_name) = page.filters.take().and_then(|f| f.department_name) {
             condition = condition.add(Column::DepartmentName.contains(department_name));
         }
+        paginate(&state.conn, select.filter(condition), &page, &SORTING).await
+    }
+}
 
-        let vec = Department::find()
-            .filter(condition)
-            .all(&state.conn)
-            .await?;
-        Ok(PageResult::new(0, 0, vec.clone(), (&vec).len() as u64))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_descendant() {
+        assert!(DepartmentService::is_descendant("/1/3/", "/1/3/"));
+        assert!(DepartmentService::is_descendant("/1/3/7/", "/1/3/"));
+        assert!(!DepartmentService::is_descendant("/1/31/", "/1/3/"));
+        assert!(!DepartmentService::is_descendant("/1/", "/1/3/"));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,74 @@
+use actix_web::web::Data;
+use serde::Serialize;
+use crate::{AppState, UserError};
+use crate::common::auth_user::AuthUser;
+use crate::common::config::IMPERSONATION_EXPIRES_SECS;
+use crate::common::security::{Actor, Security};
+use crate::common::soft_delete::SoftDelete;
+use crate::entity::prelude::User;
+use crate::service::audit_service::{AuditService, IMPERSONATION_START, IMPERSONATION_STOP};
+use crate::service::token_revocation_service::TokenRevocationService;
+use crate::service::user_service::UserService;
+
+/// 管理员以其他用户身份登录（查看对方看到的菜单和数据），开始和结束都记录审计日志
+pub struct ImpersonationService;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationToken {
+    pub access_token: String,
+    pub expires_in: i64,
+    pub user_name: String,
+}
+
+impl ImpersonationService {
+
+    /// 不能嵌套模拟，不能模拟服务账号、禁用的用户或权限多于自己的用户
+    pub async fn start(state: Data<AppState>, actor: AuthUser, target_id: i32, ip: Option<String>) -> Result<ImpersonationToken, UserError> {
+        if actor.claims.act.is_some() {
+            return Err(UserError::Forbidden("nested impersonation is not allowed".to_string()));
+        }
+        if actor.claims.sid.is_empty() {
+            return Err(UserError::Forbidden("api keys can not impersonate".to_string()));
+        }
+        if actor.id == target_id {
+            return Err(UserError::Error("can not impersonate yourself".to_string()));
+        }
+        let Some(target) = User::active_by_id(target_id).one(&state.conn).await? else {
+            return Err(UserError::DbErr(sea_orm::DbErr::RecordNotFound(target_id.to_string())));
+        };
+        if target.service_account || !target.available {
+            return Err(UserError::Forbidden("user can not be impersonated".to_string()));
+        }
+        let roles = UserService::find_role_ids(state.clone(), target.id).await?;
+        let perms = UserService::find_auth_code_by_roles(state.clone(), roles.clone()).await?;
+        if perms.iter().any(|p| !actor.has_perm(p)) {
+            return Err(UserError::Forbidden("can not impersonate a user with more permissions".to_string()));
+        }
+        let access_token = Security::encode_impersonation_token(
+            target.id,
+            target.user_name.clone(),
+            actor.claims.sid.clone(),
+            roles,
+            perms,
+            Actor { sub: actor.id.to_string(), user_name: actor.user_name.clone() },
+            *IMPERSONATION_EXPIRES_SECS,
+        )?;
+        AuditService::record(&state.conn, actor.id, IMPERSONATION_START, Some(target.id), Some(target.user_name.clone()), ip).await?;
+        Ok(ImpersonationToken {
+            access_token,
+            expires_in: *IMPERSONATION_EXPIRES_SECS,
+            user_name: target.user_name,
+        })
+    }
+
+    /// 吊销模拟登录的 token，前端切回操作人自己的 token
+    pub async fn stop(state: Data<AppState>, user: AuthUser, ip: Option<String>) -> Result<(), UserError> {
+        let Some(actor_id) = user.claims.actor_id()? else {
+            return Err(UserError::Error("not impersonating".to_string()));
+        };
+        TokenRevocationService::revoke(state.clone(), &user.claims).await?;
+        AuditService::record(&state.conn, actor_id, IMPERSONATION_STOP, Some(user.id), Some(user.user_name), ip).await?;
+        Ok(())
+    }
+}
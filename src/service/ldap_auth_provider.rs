@@ -0,0 +1,332 @@
+use std::collections::HashSet;
+use std::time::Duration;
+use actix_web::web::Data;
+use async_trait::async_trait;
+use ldap3::{ldap_escape, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use log::{info, warn};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, TransactionTrait};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono::Local;
+use crate::{AppState, UserError};
+use crate::common::config::{LDAP_BASE_DN, LDAP_BIND_DN, LDAP_BIND_PASSWORD, LDAP_DEFAULT_DEPARTMENT_ID, LDAP_EMAIL_ATTRIBUTE, LDAP_GROUP_ATTRIBUTE, LDAP_GROUP_ROLE_MAP, LDAP_STARTTLS, LDAP_TIMEOUT_SECS, LDAP_URL, LDAP_USER_FILTER};
+use crate::common::security::Security;
+use crate::common::soft_delete::SoftDelete;
+use crate::entity::prelude::{SysUserRole, User};
+use crate::entity::{sys_user_role, user};
+use crate::service::auth_provider::{AuthProvider, LDAP_PROVIDER};
+use crate::service::user_service::{UserName, UserService};
+
+#[derive(Debug, Clone)]
+pub struct LdapConfig {
+    pub url: String,
+    pub bind_dn: String,
+    pub bind_password: String,
+    pub base_dn: String,
+    pub user_filter: String,
+    pub email_attribute: String,
+    pub group_attribute: String,
+    /// (组 DN, 角色 id)
+    pub group_roles: Vec<(String, i32)>,
+    pub department_id: i32,
+    pub starttls: bool,
+    pub timeout: Duration,
+}
+
+impl LdapConfig {
+
+    /// 没有配置 `LDAP_URL` 时返回 None
+    pub fn from_env() -> Result<Option<Self>, String> {
+        if LDAP_URL.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(LdapConfig {
+            url: LDAP_URL.clone(),
+            bind_dn: LDAP_BIND_DN.clone(),
+            bind_password: LDAP_BIND_PASSWORD.clone(),
+            base_dn: LDAP_BASE_DN.clone(),
+            user_filter: LDAP_USER_FILTER.clone(),
+            email_attribute: LDAP_EMAIL_ATTRIBUTE.clone(),
+            group_attribute: LDAP_GROUP_ATTRIBUTE.clone(),
+            group_roles: parse_group_roles(&LDAP_GROUP_ROLE_MAP)?,
+            department_id: *LDAP_DEFAULT_DEPARTMENT_ID,
+            starttls: *LDAP_STARTTLS,
+            timeout: Duration::from_secs(*LDAP_TIMEOUT_SECS),
+        }))
+    }
+}
+
+/// 解析 `组DN:角色id;组DN:角色id`，DN 中含有冒号时以最后一个冒号分隔
+fn parse_group_roles(value: &str) -> Result<Vec<(String, i32)>, String> {
+    value.split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|item| {
+            let (dn, role) = item.rsplit_once(':')
+                .ok_or_else(|| format!("invalid LDAP_GROUP_ROLE_MAP item {}, expected dn:roleId", item))?;
+            let role = role.trim().parse::<i32>()
+                .map_err(|_| format!("invalid role id in LDAP_GROUP_ROLE_MAP item {}", item))?;
+            Ok((normalize_dn(dn), role))
+        })
+        .collect()
+}
+
+/// DN 比较时忽略大小写和逗号两侧的空格
+fn normalize_dn(dn: &str) -> String {
+    dn.split(',')
+        .map(|rdn| rdn.trim().to_lowercase())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn ldap_error(e: ldap3::LdapError) -> UserError {
+    UserError::Error(format!("ldap error: {}", e))
+}
+
+/// 目录中的用户
+#[derive(Debug, Clone)]
+pub struct LdapUser {
+    pub dn: String,
+    pub email: Option<String>,
+    pub groups: Vec<String>,
+}
+
+/// LDAP 认证：用查找账号按过滤器找到用户 DN，再用用户的 DN 和密码绑定（simple bind）。
+/// 首次登录时创建本地用户并固定为 LDAP 认证，每次登录按组同步映射的角色
+#[derive(Debug)]
+pub struct LdapAuthProvider {
+    config: LdapConfig,
+}
+
+impl LdapAuthProvider {
+
+    pub fn new(config: LdapConfig) -> Self {
+        LdapAuthProvider { config }
+    }
+
+    /// 校验用户名密码，用户不存在或密码错误返回 None
+    pub async fn bind(&self, username: &str, password: &str) -> Result<Option<LdapUser>, UserError> {
+        // 空密码的 simple bind 是匿名绑定，总会成功
+        if username.is_empty() || password.is_empty() {
+            return Ok(None);
+        }
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(self.config.timeout)
+            .set_starttls(self.config.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.config.url).await.map_err(ldap_error)?;
+        ldap3::drive!(conn);
+        ldap.with_timeout(self.config.timeout);
+        if !self.config.bind_dn.is_empty() {
+            ldap.simple_bind(&self.config.bind_dn, &self.config.bind_password).await
+                .and_then(|r| r.success())
+                .map_err(ldap_error)?;
+        }
+        let filter = self.config.user_filter.replace("{username}", &ldap_escape(username));
+        ldap.with_timeout(self.config.timeout);
+        let (entries, _) = ldap.search(
+            &self.config.base_dn,
+            Scope::Subtree,
+            &filter,
+            vec![self.config.email_attribute.as_str(), self.config.group_attribute.as_str()],
+        ).await
+            .and_then(|r| r.success())
+            .map_err(ldap_error)?;
+        // 找不到或不唯一都按认证失败处理
+        let mut entries = entries.into_iter();
+        let (Some(entry), None) = (entries.next(), entries.next()) else {
+            let _ = ldap.unbind().await;
+            return Ok(None);
+        };
+        let entry = SearchEntry::construct(entry);
+        ldap.with_timeout(self.config.timeout);
+        let result = ldap.simple_bind(&entry.dn, password).await.map_err(ldap_error)?;
+        let _ = ldap.unbind().await;
+        if result.rc != 0 {
+            return Ok(None);
+        }
+        Ok(Some(LdapUser {
+            email: entry.attrs.get(&self.config.email_attribute).and_then(|v| v.first().cloned()),
+            groups: entry.attrs.get(&self.config.group_attribute).cloned().unwrap_or_default(),
+            dn: entry.dn,
+        }))
+    }
+
+    /// 只接管新用户和已固定为 LDAP 的用户，同名的本地用户不能由目录账号登录
+    fn accepts(user: Option<&UserName>) -> bool {
+        user.is_none_or(|u| u.auth_provider.as_deref() == Some(LDAP_PROVIDER))
+    }
+
+    async fn is_trashed(state: &Data<AppState>, username: &str) -> Result<bool, UserError> {
+        let count = User::trashed()
+            .filter(user::Column::UserName.eq(username))
+            .count(&state.conn)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// 用户所在组映射的角色
+    fn role_ids(&self, groups: &[String]) -> Vec<i32> {
+        let groups: HashSet<String> = groups.iter().map(|g| normalize_dn(g)).collect();
+        let mut roles: Vec<i32> = self.config.group_roles.iter()
+            .filter(|(dn, _)| groups.contains(dn))
+            .map(|(_, role)| *role)
+            .collect();
+        roles.sort();
+        roles.dedup();
+        roles
+    }
+
+    /// 创建或更新本地用户，映射的角色按组重新分配，其他手动分配的角色不变
+    async fn sync_user(&self, state: Data<AppState>, user: Option<&UserName>, username: &str, ldap_user: &LdapUser) -> Result<i32, UserError> {
+        let now = Local::now().naive_local();
+        let txn = state.conn.begin().await?;
+        let user_id = match user {
+            Some(user) => {
+                if let Some(email) = &ldap_user.email {
+                    User::update_many()
+                        .col_expr(user::Column::Email, Expr::value(email.clone()))
+                        .filter(user::Column::Id.eq(user.id))
+                        .exec(&txn)
+                        .await?;
+                }
+                user.id
+            }
+            None => {
+                info!("provision ldap user {} ({})", username, ldap_user.dn);
+                let model = user::ActiveModel {
+                    id: NotSet,
+                    email: Set(ldap_user.email.clone()),
+                    user_name: Set(username.to_string()),
+                    password: Set(Security::hash_password(&Security::generate_opaque_token())?),
+                    available: Set(true),
+                    service_account: Set(false),
+                    auth_provider: Set(Some(LDAP_PROVIDER.to_string())),
+                    sex: Set(0),
+                    mobile: Set(String::new()),
+                    telephone: Set(None),
+                    department_id: Set(self.config.department_id),
+                    last_login_time: NotSet,
+                    last_login_ip: NotSet,
+                    password_changed_at: Set(Some(now)),
+                    updated_at: NotSet,
+                    created_at: Set(now),
+                    deleted_at: NotSet,
+                };
+                model.insert(&txn).await?.id
+            }
+        };
+        if !self.config.group_roles.is_empty() {
+            let mapped: Vec<i32> = self.config.group_roles.iter().map(|(_, role)| *role).collect();
+            SysUserRole::soft_delete(now)
+                .filter(sys_user_role::Column::UserId.eq(user_id))
+                .filter(sys_user_role::Column::RoleId.is_in(mapped))
+                .exec(&txn)
+                .await?;
+            let models: Vec<sys_user_role::ActiveModel> = self.role_ids(&ldap_user.groups).into_iter()
+                .map(|role_id| sys_user_role::ActiveModel {
+                    id: NotSet,
+                    role_id: Set(role_id),
+                    user_id: Set(user_id),
+                    updated_at: NotSet,
+                    created_at: Set(now),
+                    deleted_at: NotSet,
+                })
+                .collect();
+            if !models.is_empty() {
+                SysUserRole::insert_many(models).exec(&txn).await?;
+            }
+        }
+        txn.commit().await?;
+        UserService::clear_grants();
+        Ok(user_id)
+    }
+}
+
+#[async_trait]
+impl AuthProvider for LdapAuthProvider {
+    fn name(&self) -> &'static str {
+        LDAP_PROVIDER
+    }
+
+    async fn authenticate(&self, state: Data<AppState>, user: Option<&UserName>, username: &str, password: &str) -> Result<Option<i32>, UserError> {
+        if !Self::accepts(user) {
+            return Ok(None);
+        }
+        // 回收站中的同名用户仍占用用户名，不自动恢复，由管理员处理
+        if user.is_none() && Self::is_trashed(&state, username).await? {
+            warn!("ldap user {} is in the recycle bin, sign in refused", username);
+            return Ok(None);
+        }
+        let Some(ldap_user) = self.bind(username, password).await? else {
+            return Ok(None);
+        };
+        Ok(Some(self.sync_user(state, user, username, &ldap_user).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use chrono::NaiveDateTime;
+    use crate::service::ldap_auth_provider::{parse_group_roles, LdapAuthProvider, LdapConfig};
+    use crate::service::user_service::UserName;
+
+    fn config(url: &str) -> LdapConfig {
+        LdapConfig {
+            url: url.to_string(),
+            bind_dn: "cn=admin,dc=example,dc=org".to_string(),
+            bind_password: "admin".to_string(),
+            base_dn: "dc=example,dc=org".to_string(),
+            user_filter: "(uid={username})".to_string(),
+            email_attribute: "mail".to_string(),
+            group_attribute: "memberOf".to_string(),
+            group_roles: parse_group_roles("cn=admins,ou=groups,dc=example,dc=org:1; CN=Staff, OU=Groups, DC=example, DC=org:2").unwrap(),
+            department_id: 1,
+            starttls: false,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_group_roles() {
+        let provider = LdapAuthProvider::new(config("ldap://localhost:389"));
+        let groups = vec!["cn=Staff,ou=Groups,dc=example,dc=org".to_string(), "cn=other,dc=example,dc=org".to_string()];
+        assert_eq!(vec![2], provider.role_ids(&groups));
+        assert!(parse_group_roles("cn=admins").is_err());
+        assert!(parse_group_roles("cn=admins:x").is_err());
+        assert!(parse_group_roles("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_accepts() {
+        let user = |auth_provider: Option<&str>| UserName {
+            id: 1,
+            password: String::new(),
+            available: true,
+            service_account: false,
+            auth_provider: auth_provider.map(|s| s.to_string()),
+            password_changed_at: NaiveDateTime::default(),
+        };
+        assert!(LdapAuthProvider::accepts(None));
+        assert!(LdapAuthProvider::accepts(Some(&user(Some("ldap")))));
+        assert!(!LdapAuthProvider::accepts(Some(&user(None))));
+        assert!(!LdapAuthProvider::accepts(Some(&user(Some("local")))));
+    }
+
+    /// 需要本地 LDAP 服务器，例如：
+    /// `docker run -p 389:389 -e LDAP_ORGANISATION=example -e LDAP_DOMAIN=example.org -e LDAP_ADMIN_PASSWORD=admin osixia/openldap`，
+    /// 添加 `uid=alice,ou=people,dc=example,dc=org`（密码 alice）后执行
+    /// `LDAP_TEST_URL=ldap://localhost:389 cargo test -- --ignored`
+    #[actix_web::test]
+    #[ignore = "requires a local ldap server"]
+    async fn test_bind() {
+        let url = std::env::var("LDAP_TEST_URL").unwrap_or_else(|_| "ldap://localhost:389".to_string());
+        let provider = LdapAuthProvider::new(config(&url));
+        let user = provider.bind("alice", "alice").await.unwrap().unwrap();
+        assert_eq!("uid=alice,ou=people,dc=example,dc=org", user.dn);
+        assert!(provider.bind("alice", "wrong").await.unwrap().is_none());
+        assert!(provider.bind("alice", "").await.unwrap().is_none());
+        assert!(provider.bind("nobody", "alice").await.unwrap().is_none());
+        assert!(provider.bind("*", "alice").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, Statement};
+use sea_orm::sqlx::types::chrono::Local;
+use crate::UserError;
+use crate::common::config::{LOGIN_ATTEMPT_STORE, LOGIN_FAILURE_WINDOW_SECS, LOGIN_LOCK_MAX_SECS, LOGIN_LOCK_SECS, LOGIN_MAX_FAILURES_PER_IP, LOGIN_MAX_FAILURES_PER_USER};
+use crate::entity::prelude::SysLoginAttempt;
+
+#[derive(Debug, Clone, Default)]
+pub struct LoginAttempt {
+    pub failures: i32,
+    pub locked_until: Option<NaiveDateTime>,
+    pub last_failed_at: Option<NaiveDateTime>,
+}
+
+impl LoginAttempt {
+    /// 上次失败已超出统计窗口且不在锁定期，可以重新计数
+    fn is_stale(&self, now: NaiveDateTime, window: Duration) -> bool {
+        self.last_failed_at.is_none_or(|t| now - t > window)
+            && self.locked_until.is_none_or(|t| t <= now)
+    }
+}
+
+/// 登录失败计数的存储，单节点用内存，集群用数据库。
+/// 并发的失败登录都要计入，所以计数和锁定都是单条原子更新，不能先读后写
+#[async_trait]
+pub trait LoginAttemptStore: Debug + Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<LoginAttempt>, DbErr>;
+    /// 失败次数加一并返回新的次数，计数已过期时从 1 开始
+    async fn increment(&self, key: &str, now: NaiveDateTime, window: Duration) -> Result<i32, DbErr>;
+    /// 锁定到 `until`，已有更晚的锁定时间时保留
+    async fn lock(&self, key: &str, until: NaiveDateTime) -> Result<(), DbErr>;
+    async fn remove(&self, key: &str) -> Result<(), DbErr>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryLoginAttemptStore {
+    data: Mutex<MemoryAttempts>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryAttempts {
+    attempts: HashMap<String, LoginAttempt>,
+    last_sweep: Option<NaiveDateTime>,
+}
+
+impl MemoryAttempts {
+    /// 每个统计窗口最多清理一次过期的计数，避免随机用户名和 IP 撑满内存
+    fn sweep(&mut self, now: NaiveDateTime, window: Duration) {
+        if self.last_sweep.is_some_and(|t| now - t <= window) {
+            return;
+        }
+        self.attempts.retain(|_, a| !a.is_stale(now, window));
+        self.last_sweep = Some(now);
+    }
+}
+
+#[async_trait]
+impl LoginAttemptStore for MemoryLoginAttemptStore {
+    async fn get(&self, key: &str) -> Result<Option<LoginAttempt>, DbErr> {
+        let data = self.data.lock().unwrap();
+        Ok(data.attempts.get(key).cloned())
+    }
+
+    async fn increment(&self, key: &str, now: NaiveDateTime, window: Duration) -> Result<i32, DbErr> {
+        let mut data = self.data.lock().unwrap();
+        data.sweep(now, window);
+        let attempt = data.attempts.entry(key.to_string()).or_default();
+        if attempt.is_stale(now, window) {
+            *attempt = LoginAttempt::default();
+        }
+        attempt.failures += 1;
+        attempt.last_failed_at = Some(now);
+        Ok(attempt.failures)
+    }
+
+    async fn lock(&self, key: &str, until: NaiveDateTime) -> Result<(), DbErr> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(attempt) = data.attempts.get_mut(key) {
+            attempt.locked_until = attempt.locked_until.max(Some(until));
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), DbErr> {
+        let mut data = self.data.lock().unwrap();
+        data.attempts.remove(key);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct DbLoginAttemptStore {
+    conn: DatabaseConnection,
+}
+
+#[async_trait]
+impl LoginAttemptStore for DbLoginAttemptStore {
+    async fn get(&self, key: &str) -> Result<Option<LoginAttempt>, DbErr> {
+        let option = SysLoginAttempt::find_by_id(key.to_string())
+            .one(&self.conn)
+            .await?;
+        Ok(option.map(|m| LoginAttempt {
+            failures: m.failures,
+            locked_until: m.locked_until,
+            last_failed_at: m.last_failed_at,
+        }))
+    }
+
+    async fn increment(&self, key: &str, now: NaiveDateTime, window: Duration) -> Result<i32, DbErr> {
+        // 与 LoginAttempt::is_stale 相同的条件，SET 中引用的都是更新前的值
+        let stale = r#"("sys_login_attempt"."last_failed_at" IS NULL OR "sys_login_attempt"."last_failed_at" < $3)
+            AND ("sys_login_attempt"."locked_until" IS NULL OR "sys_login_attempt"."locked_until" <= $2)"#;
+        let sql = format!(
+            r#"INSERT INTO "sys_login_attempt" ("key", "failures", "last_failed_at", "updated_at", "created_at")
+            VALUES ($1, 1, $2, $2, $2)
+            ON CONFLICT ("key") DO UPDATE SET
+                "failures" = CASE WHEN {stale} THEN 1 ELSE "sys_login_attempt"."failures" + 1 END,
+                "locked_until" = CASE WHEN {stale} THEN NULL ELSE "sys_login_attempt"."locked_until" END,
+                "last_failed_at" = $2,
+                "updated_at" = $2
+            RETURNING "failures""#
+        );
+        let stmt = Statement::from_sql_and_values(
+            self.conn.get_database_backend(),
+            sql,
+            [key.into(), now.into(), (now - window).into()],
+        );
+        let row = self.conn.query_one(stmt).await?
+            .ok_or_else(|| DbErr::RecordNotFound(key.to_string()))?;
+        row.try_get("", "failures")
+    }
+
+    async fn lock(&self, key: &str, until: NaiveDateTime) -> Result<(), DbErr> {
+        let stmt = Statement::from_sql_and_values(
+            self.conn.get_database_backend(),
+            r#"UPDATE "sys_login_attempt" SET "locked_until" = GREATEST("locked_until", $2) WHERE "key" = $1"#,
+            [key.into(), until.into()],
+        );
+        self.conn.execute(stmt).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), DbErr> {
+        SysLoginAttempt::delete_by_id(key.to_string())
+            .exec(&self.conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// 按 `LOGIN_ATTEMPT_STORE` 创建存储
+pub fn create_login_attempt_store(conn: DatabaseConnection) -> Arc<dyn LoginAttemptStore> {
+    match LOGIN_ATTEMPT_STORE.as_str() {
+        "database" => Arc::new(DbLoginAttemptStore { conn }),
+        _ => Arc::new(MemoryLoginAttemptStore::default()),
+    }
+}
+
+/// 连续失败 `failures` 次后的锁定时长，未达到阈值时不锁定。
+/// 达到阈值后从 `LOGIN_LOCK_SECS` 开始每次翻倍，最长 `LOGIN_LOCK_MAX_SECS`。
+fn lock_duration(failures: i32, max_failures: i32, base_secs: i64, max_secs: i64) -> Option<Duration> {
+    if max_failures <= 0 || failures < max_failures {
+        return None;
+    }
+    let exp = (failures - max_failures).min(30) as u32;
+    let secs = base_secs.saturating_mul(1i64 << exp).min(max_secs);
+    Some(Duration::seconds(secs))
+}
+
+/// 登录失败计数：按用户名锁定账号，按 IP 限流
+pub struct LoginAttemptService;
+
+impl LoginAttemptService {
+
+    fn user_key(user_name: &str) -> String {
+        format!("user:{}", user_name)
+    }
+
+    fn ip_key(ip: &str) -> String {
+        format!("ip:{}", ip)
+    }
+
+    fn retry_after(attempt: &Option<LoginAttempt>, now: NaiveDateTime) -> Option<i64> {
+        attempt.as_ref()
+            .and_then(|a| a.locked_until)
+            .filter(|until| *until > now)
+            .map(|until| (until - now).num_seconds().max(1))
+    }
+
+    /// 登录前检查用户名或 IP 是否处于锁定期
+    pub async fn check(store: &dyn LoginAttemptStore, user_name: &str, ip: Option<&str>) -> Result<(), UserError> {
+        let now = Local::now().naive_local();
+        let attempt = store.get(&Self::user_key(user_name)).await?;
+        if let Some(retry_after) = Self::retry_after(&attempt, now) {
+            return Err(UserError::AccountLocked(retry_after));
+        }
+        if let Some(ip) = ip {
+            let attempt = store.get(&Self::ip_key(ip)).await?;
+            if let Some(retry_after) = Self::retry_after(&attempt, now) {
+                return Err(UserError::TooManyAttempts(retry_after));
+            }
+        }
+        Ok(())
+    }
+
+    async fn record(store: &dyn LoginAttemptStore, key: &str, max_failures: i32) -> Result<(), UserError> {
+        let now = Local::now().naive_local();
+        let failures = store.increment(key, now, Duration::seconds(*LOGIN_FAILURE_WINDOW_SECS)).await?;
+        if let Some(d) = lock_duration(failures, max_failures, *LOGIN_LOCK_SECS, *LOGIN_LOCK_MAX_SECS) {
+            store.lock(key, now + d).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn record_failure(store: &dyn LoginAttemptStore, user_name: &str, ip: Option<&str>) -> Result<(), UserError> {
+        Self::record(store, &Self::user_key(user_name), *LOGIN_MAX_FAILURES_PER_USER).await?;
+        if let Some(ip) = ip {
+            Self::record(store, &Self::ip_key(ip), *LOGIN_MAX_FAILURES_PER_IP).await?;
+        }
+        Ok(())
+    }
+
+    /// 登录成功或管理员解锁时清除用户名的失败计数
+    pub async fn reset(store: &dyn LoginAttemptStore, user_name: &str) -> Result<(), UserError> {
+        store.remove(&Self::user_key(user_name)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDateTime};
+    use futures::future::join_all;
+    use crate::UserError;
+    use crate::service::login_attempt_service::{lock_duration, LoginAttemptService, LoginAttemptStore, MemoryLoginAttemptStore};
+
+    #[test]
+    fn test_lock_duration() {
+        assert_eq!(None, lock_duration(4, 5, 60, 3600));
+        assert_eq!(Some(Duration::seconds(60)), lock_duration(5, 5, 60, 3600));
+        assert_eq!(Some(Duration::seconds(120)), lock_duration(6, 5, 60, 3600));
+        assert_eq!(Some(Duration::seconds(3600)), lock_duration(20, 5, 60, 3600));
+        assert_eq!(None, lock_duration(100, 0, 60, 3600));
+    }
+
+    // 以下使用默认配置：每个用户 5 次，每个 IP 20 次
+
+    #[actix_web::test]
+    async fn test_lockout_and_reset() {
+        let store = MemoryLoginAttemptStore::default();
+        for _ in 0..4 {
+            LoginAttemptService::record_failure(&store, "alice", None).await.unwrap();
+        }
+        assert!(LoginAttemptService::check(&store, "alice", None).await.is_ok());
+        // 登录成功清零，再失败 4 次仍未锁定
+        LoginAttemptService::reset(&store, "alice").await.unwrap();
+        for _ in 0..4 {
+            LoginAttemptService::record_failure(&store, "alice", None).await.unwrap();
+        }
+        assert!(LoginAttemptService::check(&store, "alice", None).await.is_ok());
+
+        LoginAttemptService::record_failure(&store, "alice", None).await.unwrap();
+        let err = LoginAttemptService::check(&store, "alice", None).await.unwrap_err();
+        assert!(matches!(err, UserError::AccountLocked(_)));
+        assert!(LoginAttemptService::check(&store, "bob", None).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_ip_throttle() {
+        let store = MemoryLoginAttemptStore::default();
+        for i in 0..20 {
+            LoginAttemptService::record_failure(&store, &format!("user{}", i), Some("1.2.3.4")).await.unwrap();
+        }
+        let err = LoginAttemptService::check(&store, "carol", Some("1.2.3.4")).await.unwrap_err();
+        assert!(matches!(err, UserError::TooManyAttempts(_)));
+        assert!(LoginAttemptService::check(&store, "carol", Some("5.6.7.8")).await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_memory_store() {
+        let store = MemoryLoginAttemptStore::default();
+        let now = NaiveDateTime::default();
+        let window = Duration::seconds(900);
+        // 并发的失败都要计入
+        let mut counts = join_all((0..10).map(|_| store.increment("k", now, window))).await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, (1..=10).collect::<Vec<_>>());
+
+        // 锁定期内不过期，锁定期和统计窗口都过去后被清理
+        store.lock("k", now + Duration::seconds(1800)).await.unwrap();
+        let later = now + Duration::seconds(1000);
+        store.increment("other", later, window).await.unwrap();
+        assert_eq!(store.get("k").await.unwrap().unwrap().failures, 10);
+        let later = now + Duration::seconds(2000);
+        store.increment("other", later, window).await.unwrap();
+        assert!(store.get("k").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+use async_trait::async_trait;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use log::{error, info};
+use sea_orm::sqlx::types::chrono::Local;
+use crate::UserError;
+use crate::common::config::{MAIL_FROM, MAIL_OUTBOX_DIR, MAIL_SENDER, SMTP_HOST, SMTP_PASSWORD, SMTP_PORT, SMTP_USERNAME};
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// 邮件发送，生产环境用 SMTP，测试和没有邮件服务器的私有部署用 outbox
+#[async_trait]
+pub trait MailSender: Debug + Send + Sync {
+    async fn send(&self, mail: Mail) -> Result<(), UserError>;
+}
+
+#[derive(Debug)]
+pub struct SmtpMailSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailSender {
+    pub fn new(host: &str, port: u16, username: &str, password: &str) -> Result<Self, UserError> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .map_err(|e| UserError::Error(e.to_string()))?
+            .port(port);
+        if !username.is_empty() {
+            builder = builder.credentials(Credentials::new(username.to_string(), password.to_string()));
+        }
+        Ok(SmtpMailSender { transport: builder.build() })
+    }
+}
+
+#[async_trait]
+impl MailSender for SmtpMailSender {
+    async fn send(&self, mail: Mail) -> Result<(), UserError> {
+        let message = Message::builder()
+            .from(MAIL_FROM.parse().map_err(|_| UserError::Error("invalid MAIL_FROM".to_string()))?)
+            .to(mail.to.parse().map_err(|_| UserError::ValidationError { field: "email".to_string() })?)
+            .subject(mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body)
+            .map_err(|e| UserError::Error(e.to_string()))?;
+        self.transport.send(message).await
+            .map_err(|e| UserError::Error(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// 不真正发送邮件：打印日志，配置了目录时同时写入文件
+#[derive(Debug, Default)]
+pub struct OutboxMailSender {
+    dir: Option<PathBuf>,
+}
+
+impl OutboxMailSender {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        OutboxMailSender { dir }
+    }
+}
+
+#[async_trait]
+impl MailSender for OutboxMailSender {
+    async fn send(&self, mail: Mail) -> Result<(), UserError> {
+        info!("mail to {}: {}", mail.to, mail.subject);
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let content = format!("To: {}\nSubject: {}\n\n{}\n", mail.to, mail.subject, mail.body);
+        let file = dir.join(format!("{}-{}.eml", Local::now().format("%Y%m%d%H%M%S"), uuid::Uuid::new_v4()));
+        write_mail_file(dir.clone(), file, content).await
+    }
+}
+
+async fn write_mail_file(dir: PathBuf, file: PathBuf, content: String) -> Result<(), UserError> {
+    actix_web::web::block(move || {
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(&file, content)
+    })
+        .await
+        .map_err(|e| UserError::Error(e.to_string()))?
+        .map_err(|e| UserError::Error(e.to_string()))
+}
+
+/// 按 `MAIL_SENDER` 创建邮件发送方式
+pub fn create_mail_sender() -> Arc<dyn MailSender> {
+    match MAIL_SENDER.as_str() {
+        "smtp" => match SmtpMailSender::new(&SMTP_HOST, *SMTP_PORT, &SMTP_USERNAME, &SMTP_PASSWORD) {
+            Ok(sender) => Arc::new(sender),
+            Err(e) => {
+                error!("create smtp mail sender error: {}, fallback to log", e);
+                Arc::new(OutboxMailSender::default())
+            }
+        },
+        "file" => Arc::new(OutboxMailSender::new(Some(PathBuf::from(MAIL_OUTBOX_DIR.as_str())))),
+        _ => Arc::new(OutboxMailSender::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::mail_service::{Mail, MailSender, OutboxMailSender};
+
+    #[actix_web::test]
+    async fn test_outbox() {
+        let dir = std::env::temp_dir().join(format!("outbox-{}", uuid::Uuid::new_v4()));
+        let sender = OutboxMailSender::new(Some(dir.clone()));
+        sender.send(Mail {
+            to: "admin@example.com".to_string(),
+            subject: "reset password".to_string(),
+            body: "token".to_string(),
+        }).await.unwrap();
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(1, files.len());
+        let content = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(content.contains("To: admin@example.com"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,18 +1,17 @@
-use std::io::Error;
-use crate::common::result::{CommonResult, FilterParam, PageResult};
+use crate::common::pagination::{paginate, Sorting};
+use crate::common::result::{FilterParam, PageResult};
 use crate::entity::menu::Model;
 use crate::entity::menu::Column;
 use crate::entity::menu::ActiveModel;
 use crate::entity::prelude::Menu;
 use crate::{AppState, UserError};
-use actix_web::web::{Data, Json, Path, Query};
-use log::info;
-use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, Condition, DbErr, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QueryTrait};
+use actix_web::web::{Data, Json, Path};
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, DbErr, EntityTrait, NotSet, Order, QueryFilter, Select};
 use sea_orm::ActiveValue::Set;
-use sea_orm::prelude::DateTime;
 use sea_orm::sqlx::types::chrono::Local;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use crate::common::soft_delete::SoftDelete;
+use crate::common::tree::{build_tree, TreeItem, TreeNode};
 
 pub struct MenuService{}
 
@@ -52,11 +51,51 @@ pub struct SearchParams {
     pub visible:Option<bool>,
 }
 
+pub type MenuNode = TreeNode<Model>;
+
+impl TreeItem for Model {
+    fn id(&self) -> i32 {
+        self.id
+    }
+    fn father_id(&self) -> i32 {
+        self.father_id
+    }
+    fn order_num(&self) -> i32 {
+        self.order_num
+    }
+}
+
+#[derive(Deserialize,Serialize,Debug,Default)]
+pub struct TreeParams {
+    pub visible:Option<bool>,
+    pub status:Option<bool>,
+}
+
+const SORTING: Sorting<Column> = Sorting {
+    columns: &[
+        ("id", Column::Id),
+        ("orderNum", Column::OrderNum),
+        ("menuName", Column::MenuName),
+        ("code", Column::Code),
+        ("createdAt", Column::CreatedAt),
+    ],
+    default: &[(Column::OrderNum, Order::Asc), (Column::Id, Order::Asc)],
+};
+
 impl MenuService {
 
     pub async fn find_all(state: Data<AppState>, Json(params) :Json<FilterParam<SearchParams>>) -> Result<PageResult<Model>, UserError> {
+        Self::search(state, Menu::active(), params).await
+    }
+
+    /// 回收站
+    pub async fn find_trash(state: Data<AppState>, params :FilterParam<SearchParams>) -> Result<PageResult<Model>, UserError> {
+        Self::search(state, Menu::trashed(), params).await
+    }
+
+    async fn search(state: Data<AppState>, select: Select<Menu>, mut params :FilterParam<SearchParams>) -> Result<PageResult<Model>, UserError> {
         let mut condition = Condition::all();
-        if let Some(filter) = params.filters {
+        if let Some(filter) = params.filters.take() {
             if let Some(menu_name) = filter.menu_name {
                 condition = condition.add(Column::MenuName.contains(menu_name));
             }
@@ -64,12 +103,7 @@ impl MenuService {
                 condition = condition.add(Column::Visible.eq(visible));
             }
         }
-        let list = Menu::find()
-            .filter(condition)
-            .all(&state.conn)
-            .await?;
-        Ok(PageResult::new(0, 0, list.clone(), list.len() as u64))
-
+        paginate(&state.conn, select.filter(condition), &params, &SORTING).await
     }
 
     pub async fn create(state: Data<AppState>, create_params : CreateMenu) ->Result<Model, UserError> {
@@ -96,7 +130,7 @@ impl MenuService {
 
     pub async fn find_one(state: Data<AppState>,id :Path<i32>)->Result<Model, DbErr> {
         let key = id.into_inner();
-        let x = Menu::find_by_id(key).one(&state.conn).await?;
+        let x = Menu::active_by_id(key).one(&state.conn).await?;
         if let Some(s) = x {
             Ok(s)
         }else {
@@ -108,25 +142,73 @@ impl MenuService {
         let value = serde_json::to_value(&update_params)?;
         let mut result = ActiveModel::from_json(value)?;
         result.created_at = NotSet;
-        let model = result.update(&state.conn).await?;
+        let model = Menu::update(result)
+            .filter(Column::DeletedAt.is_null())
+            .exec(&state.conn)
+            .await?;
         Ok(model)
     }
 
+    /// 移入回收站
     pub async fn delete(state:Data<AppState>,del_params :DelParams)->Result<u64,UserError> {
-        let result = Menu::delete_many()
+        let result = Menu::soft_delete(Local::now().naive_local())
+            .filter(Column::Id.is_in(del_params.ids))
+            .exec(&state.conn)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    pub async fn restore(state:Data<AppState>,del_params :DelParams)->Result<u64,UserError> {
+        let result = Menu::restore()
             .filter(Column::Id.is_in(del_params.ids))
             .exec(&state.conn)
             .await?;
+        Ok(result.rows_affected)
+    }
 
+    /// 彻底删除回收站中的菜单
+    pub async fn purge(state:Data<AppState>,del_params :DelParams)->Result<u64,UserError> {
+        let result = Menu::purge()
+            .filter(Column::Id.is_in(del_params.ids))
+            .exec(&state.conn)
+            .await?;
         Ok(result.rows_affected)
     }
 
     pub async fn get_menu_by_user_auth_code(state:Data<AppState>, auth_code:Vec<String>) ->Result<Vec<Model>,UserError> {
-        let vec = Menu::find()
+        let vec = Menu::active()
             .filter(Column::Code.is_in(auth_code))
             .all(&state.conn)
             .await?;
         Ok(vec)
     }
 
-}
\ No newline at end of file
+    /// 菜单管理使用的完整菜单树
+    pub async fn find_tree(state:Data<AppState>, params:TreeParams) ->Result<Vec<MenuNode>,UserError> {
+        let mut condition = Condition::all();
+        if let Some(visible) = params.visible {
+            condition = condition.add(Column::Visible.eq(visible));
+        }
+        if let Some(status) = params.status {
+            condition = condition.add(Column::Status.eq(status));
+        }
+        let vec = Menu::active()
+            .filter(condition)
+            .all(&state.conn)
+            .await?;
+        Ok(build_tree(vec, 0))
+    }
+
+    /// 用户有权限、启用且可见的菜单树
+    pub async fn get_menu_tree_by_user_auth_code(state:Data<AppState>, auth_code:Vec<String>) ->Result<Vec<MenuNode>,UserError> {
+        let vec = Menu::active()
+            .filter(Column::Code.is_in(auth_code))
+            .filter(Column::Status.ne(false).or(Column::Status.is_null()))
+            .filter(Column::Visible.ne(false).or(Column::Visible.is_null()))
+            .all(&state.conn)
+            .await?;
+        Ok(build_tree(vec, 0))
+    }
+
+}
@@ -0,0 +1,281 @@
+use actix_web::web::Data;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::RngCore;
+use sea_orm::{ColumnTrait, Condition, ConnectionTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, TransactionTrait};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::sqlx::types::chrono::Local;
+use serde::{Deserialize, Serialize};
+use totp_rs::{Algorithm, Secret, TOTP};
+use crate::{AppState, UserError};
+use crate::common::config::MFA_ISSUER;
+use crate::common::security::Security;
+use crate::common::soft_delete::SoftDelete;
+use crate::entity::prelude::{Role, SysMfaRecoveryCode, SysUserMfa};
+use crate::entity::{role, sys_mfa_recovery_code, sys_user_mfa};
+use crate::service::user_service::UserService;
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECS: u64 = 30;
+/// 允许前后各偏差一个时间窗口
+const TOTP_SKEW: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// 基于 TOTP（RFC 6238）的两步验证
+pub struct MfaService;
+
+/// 绑定验证器所需的密钥和 otpauth 地址（前端生成二维码）
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+fn totp(secret: &str, account: &str) -> Result<TOTP, UserError> {
+    let bytes = Secret::Encoded(secret.to_string()).to_bytes()
+        .map_err(|e| UserError::Error(e.to_string()))?;
+    TOTP::new(Algorithm::SHA1, TOTP_DIGITS, TOTP_SKEW as u8, TOTP_STEP_SECS, bytes, Some(MFA_ISSUER.clone()), account.replace(':', "_"))
+        .map_err(|e| UserError::Error(e.to_string()))
+}
+
+/// 验证码对应的时间窗口序号，不匹配时返回 None
+fn matched_step(totp: &TOTP, code: &str, now: u64) -> Option<i64> {
+    let current = now / TOTP_STEP_SECS;
+    (current.saturating_sub(TOTP_SKEW)..=current + TOTP_SKEW)
+        .find(|step| totp.generate(step * TOTP_STEP_SECS) == code)
+        .map(|step| step as i64)
+}
+
+fn new_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    Secret::Raw(bytes.to_vec()).to_encoded().to_string()
+}
+
+/// 尚未确认的绑定
+fn pending_model(user_id: i32, secret: &str) -> sys_user_mfa::ActiveModel {
+    let now = Local::now().naive_local();
+    sys_user_mfa::ActiveModel {
+        user_id: Set(user_id),
+        secret: Set(secret.to_string()),
+        enabled_at: Set(None),
+        last_used_step: Set(None),
+        updated_at: Set(Some(now)),
+        created_at: Set(now),
+    }
+}
+
+/// 恢复码不区分大小写，忽略分隔符
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+impl MfaService {
+
+    pub async fn find<C>(conn: &C, user_id: i32) -> Result<Option<sys_user_mfa::Model>, UserError>
+    where C: ConnectionTrait {
+        Ok(SysUserMfa::find_by_id(user_id).one(conn).await?)
+    }
+
+    /// 用户的任一角色要求两步验证
+    pub async fn is_required(state: Data<AppState>, user_id: i32) -> Result<bool, UserError> {
+        let roles = UserService::find_role_ids(state.clone(), user_id).await?;
+        if roles.is_empty() {
+            return Ok(false);
+        }
+        let count = Role::active()
+            .filter(role::Column::Id.is_in(roles))
+            .filter(role::Column::RequireMfa.eq(true))
+            .count(&state.conn)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// 生成新的密钥，确认之前不生效；已启用时需要先关闭
+    pub async fn enroll(state: Data<AppState>, user_id: i32, user_name: &str) -> Result<MfaEnrollment, UserError> {
+        if let Some(mfa) = Self::find(&state.conn, user_id).await? {
+            if mfa.enabled_at.is_some() {
+                return Err(UserError::Error("mfa is already enabled".to_string()));
+            }
+        }
+        let secret = new_secret();
+        let otpauth_uri = totp(&secret, user_name)?.get_url();
+        SysUserMfa::insert(pending_model(user_id, &secret))
+            .on_conflict(
+                OnConflict::column(sys_user_mfa::Column::UserId)
+                    .update_columns([
+                        sys_user_mfa::Column::Secret,
+                        sys_user_mfa::Column::EnabledAt,
+                        sys_user_mfa::Column::LastUsedStep,
+                        sys_user_mfa::Column::UpdatedAt,
+                    ])
+                    .to_owned()
+            )
+            .exec(&state.conn)
+            .await?;
+        Ok(MfaEnrollment { secret, otpauth_uri })
+    }
+
+    /// 角色要求两步验证时登录过程中的绑定：复用尚未确认的密钥，避免重复登录或并发登录使已扫描的二维码失效；
+    /// 更换密钥只能由用户登录后调用 `enroll`
+    pub async fn pending_enrollment(state: Data<AppState>, user_id: i32, user_name: &str) -> Result<MfaEnrollment, UserError> {
+        if Self::find(&state.conn, user_id).await?.is_none() {
+            // 并发插入时保留先写入的密钥
+            SysUserMfa::insert(pending_model(user_id, &new_secret()))
+                .on_conflict(OnConflict::column(sys_user_mfa::Column::UserId).do_nothing().to_owned())
+                .exec_without_returning(&state.conn)
+                .await?;
+        }
+        let Some(mfa) = Self::find(&state.conn, user_id).await? else {
+            return Err(UserError::Error("mfa enrollment is missing".to_string()));
+        };
+        if mfa.enabled_at.is_some() {
+            return Err(UserError::Error("mfa is already enabled".to_string()));
+        }
+        let otpauth_uri = totp(&mfa.secret, user_name)?.get_url();
+        Ok(MfaEnrollment { secret: mfa.secret, otpauth_uri })
+    }
+
+    /// 校验 TOTP 验证码，同一个时间窗口的验证码只能使用一次
+    async fn verify_totp(state: Data<AppState>, mfa: &sys_user_mfa::Model, code: &str) -> Result<bool, UserError> {
+        let totp = totp(&mfa.secret, "")?;
+        let now = Local::now().timestamp() as u64;
+        let Some(step) = matched_step(&totp, code.trim(), now) else {
+            return Ok(false);
+        };
+        let unused = Condition::any()
+            .add(sys_user_mfa::Column::LastUsedStep.is_null())
+            .add(sys_user_mfa::Column::LastUsedStep.lt(step));
+        let result = SysUserMfa::update_many()
+            .col_expr(sys_user_mfa::Column::LastUsedStep, Expr::value(step))
+            .filter(sys_user_mfa::Column::UserId.eq(mfa.user_id))
+            .filter(unused)
+            .exec(&state.conn)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// 用绑定后的第一个验证码确认启用，返回恢复码（只展示这一次）
+    pub async fn confirm(state: Data<AppState>, user_id: i32, code: &str) -> Result<Vec<String>, UserError> {
+        let Some(mfa) = Self::find(&state.conn, user_id).await? else {
+            return Err(UserError::Error("mfa is not enrolled".to_string()));
+        };
+        if mfa.enabled_at.is_some() {
+            return Err(UserError::Error("mfa is already enabled".to_string()));
+        }
+        if !Self::verify_totp(state.clone(), &mfa, code).await? {
+            return Err(UserError::InvalidMfaCode);
+        }
+        let txn = state.conn.begin().await?;
+        let now = Local::now().naive_local();
+        SysUserMfa::update_many()
+            .col_expr(sys_user_mfa::Column::EnabledAt, Expr::value(now))
+            .col_expr(sys_user_mfa::Column::UpdatedAt, Expr::value(now))
+            .filter(sys_user_mfa::Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await?;
+        let codes = Self::generate_recovery_codes(&txn, user_id).await?;
+        txn.commit().await?;
+        Ok(codes)
+    }
+
+    async fn generate_recovery_codes<C>(conn: &C, user_id: i32) -> Result<Vec<String>, UserError>
+    where C: ConnectionTrait {
+        SysMfaRecoveryCode::delete_many()
+            .filter(sys_mfa_recovery_code::Column::UserId.eq(user_id))
+            .exec(conn)
+            .await?;
+        let now = Local::now().naive_local();
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let code = Alphanumeric.sample_string(&mut rand::thread_rng(), 10).to_lowercase();
+                format!("{}-{}", &code[..5], &code[5..])
+            })
+            .collect();
+        let models: Vec<sys_mfa_recovery_code::ActiveModel> = codes.iter()
+            .map(|code| sys_mfa_recovery_code::ActiveModel {
+                id: NotSet,
+                user_id: Set(user_id),
+                code_hash: Set(Security::hash_token(&normalize_recovery_code(code))),
+                used_at: Set(None),
+                created_at: Set(now),
+            })
+            .collect();
+        SysMfaRecoveryCode::insert_many(models).exec(conn).await?;
+        Ok(codes)
+    }
+
+    /// 恢复码只能使用一次
+    async fn use_recovery_code(state: Data<AppState>, user_id: i32, code: &str) -> Result<bool, UserError> {
+        let result = SysMfaRecoveryCode::update_many()
+            .col_expr(sys_mfa_recovery_code::Column::UsedAt, Expr::value(Local::now().naive_local()))
+            .filter(sys_mfa_recovery_code::Column::UserId.eq(user_id))
+            .filter(sys_mfa_recovery_code::Column::CodeHash.eq(Security::hash_token(&normalize_recovery_code(code))))
+            .filter(sys_mfa_recovery_code::Column::UsedAt.is_null())
+            .exec(&state.conn)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// 已启用两步验证的用户校验验证码或恢复码
+    pub async fn verify(state: Data<AppState>, mfa: &sys_user_mfa::Model, code: &str) -> Result<(), UserError> {
+        if mfa.enabled_at.is_none() {
+            return Err(UserError::Error("mfa is not enabled".to_string()));
+        }
+        let ok = if code.trim().len() == TOTP_DIGITS && code.trim().chars().all(|c| c.is_ascii_digit()) {
+            Self::verify_totp(state, mfa, code).await?
+        } else {
+            Self::use_recovery_code(state, mfa.user_id, code).await?
+        };
+        if !ok {
+            return Err(UserError::InvalidMfaCode);
+        }
+        Ok(())
+    }
+
+    /// 关闭两步验证，角色要求两步验证时不允许关闭
+    pub async fn disable(state: Data<AppState>, user_id: i32, code: &str) -> Result<(), UserError> {
+        let Some(mfa) = Self::find(&state.conn, user_id).await? else {
+            return Err(UserError::Error("mfa is not enabled".to_string()));
+        };
+        Self::verify(state.clone(), &mfa, code).await?;
+        if Self::is_required(state.clone(), user_id).await? {
+            return Err(UserError::Forbidden("mfa is required by role".to_string()));
+        }
+        let txn = state.conn.begin().await?;
+        SysUserMfa::delete_by_id(user_id).exec(&txn).await?;
+        SysMfaRecoveryCode::delete_many()
+            .filter(sys_mfa_recovery_code::Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use totp_rs::{Secret, TOTP};
+    use crate::service::mfa_service::{matched_step, normalize_recovery_code, totp};
+
+    #[test]
+    fn test_matched_step() {
+        // RFC 6238 附录 B 的 SHA1 密钥，T = 59 时 6 位验证码为 287082
+        let secret = Secret::Raw(b"12345678901234567890".to_vec()).to_encoded().to_string();
+        let totp: TOTP = totp(&secret, "admin").unwrap();
+        assert_eq!(Some(1), matched_step(&totp, "287082", 59));
+        assert_eq!(Some(1), matched_step(&totp, "287082", 89));
+        assert_eq!(None, matched_step(&totp, "287082", 149));
+        assert_eq!(None, matched_step(&totp, "000000", 59));
+        assert!(totp.get_url().starts_with("otpauth://totp/"));
+    }
+
+    #[test]
+    fn test_normalize_recovery_code() {
+        assert_eq!("abcde12345", normalize_recovery_code(" ABCDE-12345 "));
+    }
+}
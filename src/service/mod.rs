@@ -4,3 +4,18 @@ pub mod permission_service;
 pub mod role_service;
 pub mod user_service;
 pub mod auth;
+pub mod refresh_token_service;
+pub mod token_revocation_service;
+pub mod session_service;
+pub mod login_attempt_service;
+pub mod mail_service;
+pub mod password_reset_service;
+pub mod mfa_service;
+pub mod api_key_service;
+pub mod oauth_service;
+pub mod oauth_client_service;
+pub mod oidc_service;
+pub mod auth_provider;
+pub mod ldap_auth_provider;
+pub mod audit_service;
+pub mod impersonation_service;
\ No newline at end of file
@@ -0,0 +1,123 @@
+use actix_web::web::Data;
+use rand::distributions::{Alphanumeric, DistString};
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, EntityTrait, NotSet, Order, QueryFilter, TransactionTrait};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono::Local;
+use serde::{Deserialize, Serialize};
+use crate::{AppState, UserError};
+use crate::common::pagination::{paginate, Sorting};
+use crate::common::result::{FilterParam, PageResult};
+use crate::common::security::Security;
+use crate::entity::prelude::{SysOauthClient, SysOauthToken};
+use crate::entity::sys_oauth_client::{ActiveModel, Column, Model};
+use crate::entity::sys_oauth_token;
+use crate::service::oauth_service::{GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS};
+
+const CLIENT_ID_LEN: usize = 24;
+
+/// 接入 OAuth2 的第三方应用
+pub struct OAuthClientService;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOAuthClient {
+    pub name: String,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub scopes: Vec<String>,
+    /// 机密客户端（服务端应用）会生成密钥，公开客户端必须使用 PKCE
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOAuthClient {
+    pub name: Option<String>,
+    pub client_id: Option<String>,
+}
+
+/// 创建时返回密钥明文，之后无法再查看
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthClientCreated {
+    pub client_secret: Option<String>,
+    #[serde(flatten)]
+    pub client: Model,
+}
+
+const SORTING: Sorting<Column> = Sorting {
+    columns: &[
+        ("id", Column::Id),
+        ("name", Column::Name),
+        ("clientId", Column::ClientId),
+        ("createdAt", Column::CreatedAt),
+    ],
+    default: &[(Column::Id, Order::Desc)],
+};
+
+impl OAuthClientService {
+
+    pub async fn create(state: Data<AppState>, dto: CreateOAuthClient) -> Result<OAuthClientCreated, UserError> {
+        if dto.grant_types.is_empty() {
+            return Err(UserError::ValidationError { field: "grantTypes".to_string() });
+        }
+        if let Some(grant) = dto.grant_types.iter().find(|g| *g != GRANT_AUTHORIZATION_CODE && *g != GRANT_CLIENT_CREDENTIALS) {
+            return Err(UserError::Error(format!("unsupported grant type {}", grant)));
+        }
+        let has_grant = |grant: &str| dto.grant_types.iter().any(|g| g == grant);
+        if has_grant(GRANT_AUTHORIZATION_CODE) && dto.redirect_uris.is_empty() {
+            return Err(UserError::ValidationError { field: "redirectUris".to_string() });
+        }
+        if has_grant(GRANT_CLIENT_CREDENTIALS) && !dto.confidential {
+            return Err(UserError::Error("client_credentials requires a confidential client".to_string()));
+        }
+        let client_secret = dto.confidential.then(Security::generate_opaque_token);
+        let model = ActiveModel {
+            id: NotSet,
+            client_id: Set(Alphanumeric.sample_string(&mut rand::thread_rng(), CLIENT_ID_LEN)),
+            client_secret_hash: Set(client_secret.as_deref().map(Security::hash_token)),
+            name: Set(dto.name),
+            redirect_uris: Set(serde_json::json!(dto.redirect_uris)),
+            grant_types: Set(serde_json::json!(dto.grant_types)),
+            scopes: Set(serde_json::json!(dto.scopes)),
+            available: Set(true),
+            updated_at: NotSet,
+            created_at: Set(Local::now().naive_local()),
+        };
+        let client = model.insert(&state.conn).await?;
+        Ok(OAuthClientCreated { client_secret, client })
+    }
+
+    pub async fn find_all(state: Data<AppState>, mut page: FilterParam<SearchOAuthClient>) -> Result<PageResult<Model>, UserError> {
+        let mut conditions = Condition::all();
+        if let Some(f) = page.filters.take() {
+            if let Some(name) = f.name {
+                conditions = conditions.add(Column::Name.contains(name));
+            }
+            if let Some(client_id) = f.client_id {
+                conditions = conditions.add(Column::ClientId.eq(client_id));
+            }
+        }
+        paginate(&state.conn, SysOauthClient::find().filter(conditions), &page, &SORTING).await
+    }
+
+    /// 删除客户端并吊销其签发的 token
+    pub async fn delete(state: Data<AppState>, id: i32) -> Result<(), UserError> {
+        let Some(client) = SysOauthClient::find_by_id(id).one(&state.conn).await? else {
+            return Err(UserError::DbErr(sea_orm::DbErr::RecordNotFound(id.to_string())));
+        };
+        let txn = state.conn.begin().await?;
+        SysOauthToken::update_many()
+            .col_expr(sys_oauth_token::Column::RevokedAt, Expr::value(Local::now().naive_local()))
+            .filter(sys_oauth_token::Column::ClientId.eq(client.client_id))
+            .filter(sys_oauth_token::Column::RevokedAt.is_null())
+            .exec(&txn)
+            .await?;
+        SysOauthClient::delete_by_id(id).exec(&txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+}
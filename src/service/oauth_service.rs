@@ -0,0 +1,582 @@
+//! OAuth2 授权服务器和 OIDC
+//!
+//! 支持授权码 + PKCE 和客户端凭证两种授权方式，access token 和 id token 使用 `JWT_KEYS` 签名，
+//! 其他应用可以通过 `/.well-known/openid-configuration` 使用标准库接入。
+//! 协议接口的错误按 RFC 6749 返回 `{"error", "error_description"}`，不使用 `CommonResult`。
+
+use std::fmt;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::web::Data;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{Duration, Utc};
+use log::error;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, NotSet, QueryFilter};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono::Local;
+use serde::{Deserialize, Serialize};
+use crate::{AppState, UserError};
+use crate::common::config::{OAUTH_ACCESS_TOKEN_EXPIRES_SECS, OAUTH_CODE_EXPIRES_SECS, OAUTH_ISSUER, OAUTH_LOGIN_URL};
+use crate::common::jwt_keys::JWT_KEYS;
+use crate::common::security::Security;
+use crate::common::soft_delete::SoftDelete;
+use crate::entity::prelude::{SysOauthClient, SysOauthCode, SysOauthToken, User};
+use crate::entity::{sys_oauth_client, sys_oauth_code, sys_oauth_token, user};
+
+pub const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+const SCOPE_OPENID: &str = "openid";
+const SCOPE_EMAIL: &str = "email";
+
+/// RFC 6749 第 5.2 节的错误响应
+#[derive(Debug)]
+pub struct OAuthError {
+    status: StatusCode,
+    error: &'static str,
+    description: String,
+}
+
+impl OAuthError {
+    fn new(status: StatusCode, error: &'static str, description: impl Into<String>) -> Self {
+        OAuthError { status, error, description: description.into() }
+    }
+
+    pub fn invalid_request(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_request", description)
+    }
+
+    pub fn invalid_client(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "invalid_client", description)
+    }
+
+    pub fn invalid_grant(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_grant", description)
+    }
+
+    pub fn unauthorized_client(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "unauthorized_client", description)
+    }
+
+    pub fn invalid_scope(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_scope", description)
+    }
+
+    pub fn invalid_token(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "invalid_token", description)
+    }
+
+    pub fn access_denied(description: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "access_denied", description)
+    }
+}
+
+impl fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.error, self.description)
+    }
+}
+
+impl ResponseError for OAuthError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut builder = HttpResponse::build(self.status);
+        if self.status == StatusCode::UNAUTHORIZED {
+            let scheme = if self.error == "invalid_token" { "Bearer" } else { "Basic" };
+            builder.insert_header((WWW_AUTHENTICATE, format!("{} error=\"{}\"", scheme, self.error)));
+        }
+        builder.json(serde_json::json!({
+            "error": self.error,
+            "error_description": self.description,
+        }))
+    }
+}
+
+impl From<DbErr> for OAuthError {
+    fn from(e: DbErr) -> Self {
+        error!("oauth db error: {}", e);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "server_error", "database error")
+    }
+}
+
+impl From<UserError> for OAuthError {
+    fn from(e: UserError) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "server_error", e.to_string())
+    }
+}
+
+/// 授权请求参数，`GET /oauth/authorize` 的查询参数和 `POST /oauth/consent` 的请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizeResult {
+    /// 带上 code 和 state 的回调地址，前端直接跳转
+    pub redirect_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenParam {
+    pub token: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Introspection {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserInfo {
+    pub sub: String,
+    pub preferred_username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AccessClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    client_id: String,
+    scope: String,
+    exp: usize,
+    iat: usize,
+    jti: String,
+}
+
+#[derive(Debug, Serialize)]
+struct IdClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    exp: usize,
+    iat: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    preferred_username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+}
+
+fn json_strings(value: &serde_json::Value) -> Vec<String> {
+    serde_json::from_value(value.clone()).unwrap_or_default()
+}
+
+fn split_scope(scope: Option<&str>) -> Vec<String> {
+    scope.unwrap_or_default()
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// PKCE（RFC 7636）只支持 S256
+fn verify_pkce(challenge: &str, method: Option<&str>, verifier: &str) -> bool {
+    method == Some("S256") && Security::pkce_challenge(verifier) == challenge
+}
+
+/// 在回调地址上追加查询参数
+fn append_query(uri: &str, params: &[(&str, &str)]) -> String {
+    let query = serde_urlencoded::to_string(params).unwrap_or_default();
+    let separator = if uri.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", uri, separator, query)
+}
+
+/// 客户端认证信息，优先使用 HTTP Basic，其次是表单中的 client_id / client_secret
+fn client_credentials(req: &HttpRequest, client_id: Option<String>, client_secret: Option<String>) -> (Option<String>, Option<String>) {
+    let basic = req.headers().get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Basic "))
+        .and_then(|v| STANDARD.decode(v.trim()).ok())
+        .and_then(|v| String::from_utf8(v).ok())
+        .and_then(|v| v.split_once(':').map(|(id, secret)| (id.to_string(), secret.to_string())));
+    match basic {
+        Some((id, secret)) => (Some(id), Some(secret)),
+        None => (client_id, client_secret),
+    }
+}
+
+pub struct OAuthService;
+
+impl OAuthService {
+
+    async fn find_client(state: &Data<AppState>, client_id: &str) -> Result<Option<sys_oauth_client::Model>, OAuthError> {
+        Ok(SysOauthClient::find()
+            .filter(sys_oauth_client::Column::ClientId.eq(client_id))
+            .filter(sys_oauth_client::Column::Available.eq(true))
+            .one(&state.conn)
+            .await?)
+    }
+
+    /// 认证客户端，机密客户端必须提供正确的密钥，公开客户端不能提供密钥
+    async fn authenticate_client(state: &Data<AppState>, req: &HttpRequest, client_id: Option<String>, client_secret: Option<String>) -> Result<sys_oauth_client::Model, OAuthError> {
+        let (client_id, client_secret) = client_credentials(req, client_id, client_secret);
+        let Some(client_id) = client_id else {
+            return Err(OAuthError::invalid_client("client authentication is required"));
+        };
+        let Some(client) = Self::find_client(state, &client_id).await? else {
+            return Err(OAuthError::invalid_client("client is invalid"));
+        };
+        let ok = match (&client.client_secret_hash, client_secret) {
+            (Some(hash), Some(secret)) => *hash == Security::hash_token(&secret),
+            (None, None) => true,
+            _ => false,
+        };
+        if !ok {
+            return Err(OAuthError::invalid_client("client is invalid"));
+        }
+        Ok(client)
+    }
+
+    /// 校验授权请求，返回客户端和申请的 scope
+    pub async fn validate_authorize(state: &Data<AppState>, params: &AuthorizeRequest) -> Result<(sys_oauth_client::Model, Vec<String>), OAuthError> {
+        let Some(client) = Self::find_client(state, &params.client_id).await? else {
+            return Err(OAuthError::invalid_request("client is invalid"));
+        };
+        if !json_strings(&client.redirect_uris).contains(&params.redirect_uri) {
+            return Err(OAuthError::invalid_request("redirect_uri is not registered"));
+        }
+        if params.response_type != "code" {
+            return Err(OAuthError::invalid_request("only response_type=code is supported"));
+        }
+        if !json_strings(&client.grant_types).iter().any(|g| g == GRANT_AUTHORIZATION_CODE) {
+            return Err(OAuthError::unauthorized_client("authorization_code is not allowed for this client"));
+        }
+        let scopes = split_scope(params.scope.as_deref());
+        let allowed = json_strings(&client.scopes);
+        if let Some(scope) = scopes.iter().find(|s| !allowed.contains(s)) {
+            return Err(OAuthError::invalid_scope(format!("scope {} is not allowed", scope)));
+        }
+        match (&params.code_challenge, params.code_challenge_method.as_deref()) {
+            (Some(_), Some("S256")) => {}
+            (Some(_), _) => return Err(OAuthError::invalid_request("only code_challenge_method=S256 is supported")),
+            (None, _) if client.client_secret_hash.is_none() => {
+                return Err(OAuthError::invalid_request("public clients must use PKCE"));
+            }
+            (None, _) => {}
+        }
+        Ok((client, scopes))
+    }
+
+    /// 校验通过后跳转到前端登录/授权页，原样带上授权参数
+    pub async fn login_redirect(state: Data<AppState>, params: AuthorizeRequest) -> Result<String, OAuthError> {
+        Self::validate_authorize(&state, &params).await?;
+        let query = serde_urlencoded::to_string(&params)
+            .map_err(|e| OAuthError::invalid_request(e.to_string()))?;
+        let separator = if OAUTH_LOGIN_URL.contains('?') { '&' } else { '?' };
+        Ok(format!("{}{}{}", *OAUTH_LOGIN_URL, separator, query))
+    }
+
+    /// 用户在前端登录并同意授权后签发授权码
+    pub async fn authorize(state: Data<AppState>, user_id: i32, params: AuthorizeRequest) -> Result<AuthorizeResult, OAuthError> {
+        let (client, scopes) = Self::validate_authorize(&state, &params).await?;
+        let code = Security::generate_opaque_token();
+        let now = Local::now().naive_local();
+        let model = sys_oauth_code::ActiveModel {
+            code_hash: Set(Security::hash_token(&code)),
+            client_id: Set(client.client_id),
+            user_id: Set(user_id),
+            redirect_uri: Set(params.redirect_uri.clone()),
+            scope: Set(scopes.join(" ")),
+            code_challenge: Set(params.code_challenge),
+            code_challenge_method: Set(params.code_challenge_method),
+            nonce: Set(params.nonce),
+            expires_at: Set(now + Duration::seconds(*OAUTH_CODE_EXPIRES_SECS)),
+            used_at: NotSet,
+            created_at: Set(now),
+        };
+        model.insert(&state.conn).await?;
+        let mut query = vec![("code", code.as_str())];
+        if let Some(s) = params.state.as_deref() {
+            query.push(("state", s));
+        }
+        Ok(AuthorizeResult {
+            redirect_uri: append_query(&params.redirect_uri, &query),
+        })
+    }
+
+    pub async fn token(state: Data<AppState>, req: &HttpRequest, params: TokenRequest) -> Result<TokenResponse, OAuthError> {
+        let client = Self::authenticate_client(&state, req, params.client_id.clone(), params.client_secret.clone()).await?;
+        if !json_strings(&client.grant_types).contains(&params.grant_type) {
+            return Err(OAuthError::unauthorized_client(format!("{} is not allowed for this client", params.grant_type)));
+        }
+        match params.grant_type.as_str() {
+            GRANT_AUTHORIZATION_CODE => Self::exchange_code(state, client, params).await,
+            GRANT_CLIENT_CREDENTIALS => Self::client_credentials(state, client, params).await,
+            _ => Err(OAuthError::new(StatusCode::BAD_REQUEST, "unsupported_grant_type", "grant_type is not supported")),
+        }
+    }
+
+    async fn exchange_code(state: Data<AppState>, client: sys_oauth_client::Model, params: TokenRequest) -> Result<TokenResponse, OAuthError> {
+        let Some(code) = params.code else {
+            return Err(OAuthError::invalid_request("code is required"));
+        };
+        let now = Local::now().naive_local();
+        let code_hash = Security::hash_token(&code);
+        let Some(grant) = SysOauthCode::find_by_id(code_hash.clone()).one(&state.conn).await? else {
+            return Err(OAuthError::invalid_grant("code is invalid"));
+        };
+        if grant.client_id != client.client_id || grant.expires_at <= now {
+            return Err(OAuthError::invalid_grant("code is invalid or expired"));
+        }
+        // 条件更新，授权码只能使用一次
+        let result = SysOauthCode::update_many()
+            .col_expr(sys_oauth_code::Column::UsedAt, Expr::value(now))
+            .filter(sys_oauth_code::Column::CodeHash.eq(code_hash))
+            .filter(sys_oauth_code::Column::UsedAt.is_null())
+            .exec(&state.conn)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(OAuthError::invalid_grant("code is already used"));
+        }
+        if params.redirect_uri.as_deref() != Some(grant.redirect_uri.as_str()) {
+            return Err(OAuthError::invalid_grant("redirect_uri does not match"));
+        }
+        if let Some(challenge) = &grant.code_challenge {
+            let verifier = params.code_verifier.unwrap_or_default();
+            if !verify_pkce(challenge, grant.code_challenge_method.as_deref(), &verifier) {
+                return Err(OAuthError::invalid_grant("code_verifier is invalid"));
+            }
+        }
+        let Some(user) = User::active_by_id(grant.user_id).one(&state.conn).await? else {
+            return Err(OAuthError::invalid_grant("user not found"));
+        };
+        if !user.available {
+            return Err(OAuthError::invalid_grant("account is disabled"));
+        }
+        let scopes = split_scope(Some(&grant.scope));
+        let mut response = Self::issue(&state, &client.client_id, Some(user.id), user.id.to_string(), &grant.scope).await?;
+        if scopes.iter().any(|s| s == SCOPE_OPENID) {
+            let now = Utc::now();
+            let claims = IdClaims {
+                iss: OAUTH_ISSUER.clone(),
+                sub: user.id.to_string(),
+                aud: client.client_id.clone(),
+                exp: (now + Duration::seconds(*OAUTH_ACCESS_TOKEN_EXPIRES_SECS)).timestamp() as usize,
+                iat: now.timestamp() as usize,
+                nonce: grant.nonce,
+                preferred_username: user.user_name.clone(),
+                email: if scopes.iter().any(|s| s == SCOPE_EMAIL) { user.email.clone() } else { None },
+            };
+            response.id_token = Some(Security::sign(&claims)?);
+        }
+        Ok(response)
+    }
+
+    /// 客户端凭证只允许机密客户端使用，token 的主体是客户端本身
+    async fn client_credentials(state: Data<AppState>, client: sys_oauth_client::Model, params: TokenRequest) -> Result<TokenResponse, OAuthError> {
+        if client.client_secret_hash.is_none() {
+            return Err(OAuthError::unauthorized_client("public clients can not use client_credentials"));
+        }
+        let allowed = json_strings(&client.scopes);
+        let scopes = match params.scope.as_deref() {
+            Some(scope) => split_scope(Some(scope)),
+            None => allowed.clone(),
+        };
+        if let Some(scope) = scopes.iter().find(|s| !allowed.contains(s) || *s == SCOPE_OPENID) {
+            return Err(OAuthError::invalid_scope(format!("scope {} is not allowed", scope)));
+        }
+        Self::issue(&state, &client.client_id, None, client.client_id.clone(), &scopes.join(" ")).await
+    }
+
+    async fn issue(state: &Data<AppState>, client_id: &str, user_id: Option<i32>, sub: String, scope: &str) -> Result<TokenResponse, OAuthError> {
+        let now = Utc::now();
+        let exp = now + Duration::seconds(*OAUTH_ACCESS_TOKEN_EXPIRES_SECS);
+        let claims = AccessClaims {
+            iss: OAUTH_ISSUER.clone(),
+            sub,
+            aud: client_id.to_string(),
+            client_id: client_id.to_string(),
+            scope: scope.to_string(),
+            exp: exp.timestamp() as usize,
+            iat: now.timestamp() as usize,
+            jti: uuid::Uuid::new_v4().to_string(),
+        };
+        let access_token = Security::sign(&claims)?;
+        let model = sys_oauth_token::ActiveModel {
+            jti: Set(claims.jti),
+            client_id: Set(client_id.to_string()),
+            user_id: Set(user_id),
+            scope: Set(scope.to_string()),
+            expires_at: Set(exp.with_timezone(&Local).naive_local()),
+            revoked_at: NotSet,
+            created_at: Set(Local::now().naive_local()),
+        };
+        model.insert(&state.conn).await?;
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer",
+            expires_in: *OAUTH_ACCESS_TOKEN_EXPIRES_SECS,
+            scope: scope.to_string(),
+            id_token: None,
+        })
+    }
+
+    /// 校验 access token 签名、签发方和吊销状态
+    async fn verify_access_token(state: &Data<AppState>, token: &str) -> Result<Option<AccessClaims>, OAuthError> {
+        let Ok(claims) = Security::verify_token::<AccessClaims>(token, None) else {
+            return Ok(None);
+        };
+        if claims.iss != *OAUTH_ISSUER {
+            return Ok(None);
+        }
+        let active = SysOauthToken::find_by_id(claims.jti.clone())
+            .filter(sys_oauth_token::Column::RevokedAt.is_null())
+            .one(&state.conn)
+            .await?
+            .is_some();
+        Ok(if active { Some(claims) } else { None })
+    }
+
+    /// RFC 7662 token 内省，调用方需要客户端认证
+    pub async fn introspect(state: Data<AppState>, req: &HttpRequest, params: TokenParam) -> Result<Introspection, OAuthError> {
+        Self::authenticate_client(&state, req, params.client_id, params.client_secret).await?;
+        let Some(claims) = Self::verify_access_token(&state, &params.token).await? else {
+            return Ok(Introspection::default());
+        };
+        Ok(Introspection {
+            active: true,
+            scope: Some(claims.scope),
+            client_id: Some(claims.client_id),
+            sub: Some(claims.sub),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            token_type: Some("Bearer"),
+        })
+    }
+
+    /// RFC 7009 token 吊销，只能吊销签发给本客户端的 token，未知 token 同样返回成功
+    pub async fn revoke(state: Data<AppState>, req: &HttpRequest, params: TokenParam) -> Result<(), OAuthError> {
+        let client = Self::authenticate_client(&state, req, params.client_id, params.client_secret).await?;
+        let Some(claims) = Self::verify_access_token(&state, &params.token).await? else {
+            return Ok(());
+        };
+        if claims.client_id != client.client_id {
+            return Ok(());
+        }
+        SysOauthToken::update_many()
+            .col_expr(sys_oauth_token::Column::RevokedAt, Expr::value(Local::now().naive_local()))
+            .filter(sys_oauth_token::Column::Jti.eq(claims.jti))
+            .exec(&state.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// OIDC userinfo，需要带 openid scope 的用户 token
+    pub async fn userinfo(state: Data<AppState>, token: &str) -> Result<UserInfo, OAuthError> {
+        let Some(claims) = Self::verify_access_token(&state, token).await? else {
+            return Err(OAuthError::invalid_token("access token is invalid"));
+        };
+        let scopes = split_scope(Some(&claims.scope));
+        if !scopes.iter().any(|s| s == SCOPE_OPENID) {
+            return Err(OAuthError::new(StatusCode::FORBIDDEN, "insufficient_scope", "openid scope is required"));
+        }
+        let Ok(user_id) = claims.sub.parse::<i32>() else {
+            return Err(OAuthError::invalid_token("access token has no user"));
+        };
+        let Some(user) = User::active_by_id(user_id)
+            .filter(user::Column::Available.eq(true))
+            .one(&state.conn)
+            .await? else {
+            return Err(OAuthError::invalid_token("user is disabled"));
+        };
+        Ok(UserInfo {
+            sub: user.id.to_string(),
+            preferred_username: user.user_name,
+            email: if scopes.iter().any(|s| s == SCOPE_EMAIL) { user.email } else { None },
+        })
+    }
+
+    /// OIDC discovery
+    pub fn discovery() -> serde_json::Value {
+        let issuer = OAUTH_ISSUER.trim_end_matches('/');
+        serde_json::json!({
+            "issuer": *OAUTH_ISSUER,
+            "authorization_endpoint": format!("{}/oauth/authorize", issuer),
+            "token_endpoint": format!("{}/oauth/token", issuer),
+            "userinfo_endpoint": format!("{}/oauth/userinfo", issuer),
+            "introspection_endpoint": format!("{}/oauth/introspect", issuer),
+            "revocation_endpoint": format!("{}/oauth/revoke", issuer),
+            "jwks_uri": format!("{}/.well-known/jwks.json", issuer),
+            "response_types_supported": ["code"],
+            "grant_types_supported": [GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": [format!("{:?}", JWT_KEYS.algorithm)],
+            "scopes_supported": [SCOPE_OPENID, "profile", SCOPE_EMAIL],
+            "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+            "code_challenge_methods_supported": ["S256"],
+            "claims_supported": ["sub", "iss", "aud", "exp", "iat", "nonce", "preferred_username", "email"],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::oauth_service::{append_query, verify_pkce};
+
+    #[test]
+    fn test_verify_pkce() {
+        // RFC 7636 附录 B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+        assert!(verify_pkce(challenge, Some("S256"), verifier));
+        assert!(!verify_pkce(challenge, Some("plain"), verifier));
+        assert!(!verify_pkce(challenge, Some("S256"), "wrong"));
+    }
+
+    #[test]
+    fn test_append_query() {
+        assert_eq!("https://app/cb?code=a&state=x+y", append_query("https://app/cb", &[("code", "a"), ("state", "x y")]));
+        assert_eq!("https://app/cb?v=1&code=a", append_query("https://app/cb?v=1", &[("code", "a")]));
+    }
+}
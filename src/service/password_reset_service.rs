@@ -0,0 +1,98 @@
+use actix_web::web::Data;
+use chrono::Duration;
+use log::info;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, TransactionTrait};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono::Local;
+use crate::{AppState, UserError};
+use crate::common::config::{PASSWORD_RESET_TOKEN_TTL_MINUTES, PASSWORD_RESET_URL};
+use crate::common::security::Security;
+use crate::common::soft_delete::SoftDelete;
+use crate::entity::prelude::{SysPasswordResetToken, User};
+use crate::entity::sys_password_reset_token::{ActiveModel, Column};
+use crate::entity::user;
+use crate::service::login_attempt_service::LoginAttemptService;
+use crate::service::mail_service::Mail;
+use crate::service::session_service::SessionService;
+use crate::service::user_service::UserService;
+
+/// 找回密码：通过邮箱发送一次性的重置链接
+pub struct PasswordResetService;
+
+impl PasswordResetService {
+
+    /// 邮箱不存在时同样返回成功，避免枚举邮箱
+    pub async fn forgot(state: Data<AppState>, email: String) -> Result<(), UserError> {
+        let option = User::active()
+            .filter(user::Column::Email.eq(email.clone()))
+            .filter(user::Column::Available.eq(true))
+            .one(&state.conn)
+            .await?;
+        let Some(user) = option else {
+            info!("password reset requested for unknown email {}", email);
+            return Ok(());
+        };
+        let token = Security::generate_opaque_token();
+        let now = Local::now().naive_local();
+        let txn = state.conn.begin().await?;
+        // 之前发出的链接作废
+        SysPasswordResetToken::update_many()
+            .col_expr(Column::UsedAt, Expr::value(now))
+            .filter(Column::UserId.eq(user.id))
+            .filter(Column::UsedAt.is_null())
+            .exec(&txn)
+            .await?;
+        let model = ActiveModel {
+            id: NotSet,
+            user_id: Set(user.id),
+            token_hash: Set(Security::hash_token(&token)),
+            expires_at: Set(now + Duration::minutes(*PASSWORD_RESET_TOKEN_TTL_MINUTES)),
+            used_at: NotSet,
+            created_at: Set(now),
+        };
+        model.insert(&txn).await?;
+        txn.commit().await?;
+        state.mailer.send(Mail {
+            to: email,
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Hi {},\n\nOpen the link below to reset your password, it expires in {} minutes:\n\n{}{}\n\nIf you did not request this, please ignore this email.",
+                user.user_name, *PASSWORD_RESET_TOKEN_TTL_MINUTES, *PASSWORD_RESET_URL, token
+            ),
+        }).await
+    }
+
+    /// 使用重置 token 设置新密码，token 只能使用一次，成功后吊销该用户的全部会话
+    pub async fn reset(state: Data<AppState>, token: String, new_password: String) -> Result<(), UserError> {
+        let now = Local::now().naive_local();
+        let txn = state.conn.begin().await?;
+        let option = SysPasswordResetToken::find()
+            .filter(Column::TokenHash.eq(Security::hash_token(&token)))
+            .filter(Column::UsedAt.is_null())
+            .filter(Column::ExpiresAt.gt(now))
+            .one(&txn)
+            .await?;
+        let Some(reset) = option else {
+            return Err(UserError::Error("reset token is invalid or expired".to_string()));
+        };
+        // 条件更新，保证并发情况下只能使用一次
+        let result = SysPasswordResetToken::update_many()
+            .col_expr(Column::UsedAt, Expr::value(now))
+            .filter(Column::Id.eq(reset.id))
+            .filter(Column::UsedAt.is_null())
+            .exec(&txn)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(UserError::Error("reset token is invalid or expired".to_string()));
+        }
+        let Some(user) = User::active_by_id(reset.user_id).one(&txn).await? else {
+            return Err(UserError::Error("reset token is invalid or expired".to_string()));
+        };
+        UserService::set_password(&txn, &user, &new_password).await?;
+        SessionService::revoke_all(&txn, user.id).await?;
+        txn.commit().await?;
+        LoginAttemptService::reset(state.login_attempts.as_ref(), &user.user_name).await?;
+        Ok(())
+    }
+}
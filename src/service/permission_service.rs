@@ -1,12 +1,14 @@
-use actix_web::web::{Data, Json};
-use sea_orm::{ColumnTrait, EntityTrait, NotSet, QueryFilter, QueryTrait, TransactionTrait};
+use actix_web::web::Data;
+use sea_orm::{ColumnTrait, EntityTrait, NotSet, QueryFilter, TransactionTrait};
 use sea_orm::ActiveValue::{Set, Unchanged};
 use sea_orm::sqlx::types::chrono::Local;
 use serde::{Deserialize, Serialize};
 use crate::{AppState, UserError};
+use crate::common::soft_delete::SoftDelete;
 use crate::entity::prelude::SysRolePerm;
 use crate::entity::sys_role_perm;
 use crate::entity::sys_role_perm::ActiveModel;
+use crate::service::user_service::UserService;
 
 pub struct PermissionService;
 #[derive(Serialize,Deserialize,Debug)]
@@ -23,7 +25,7 @@ impl PermissionService {
         let PermissionAssignRoleMenuReqDto{role_id,perm_codes} = dto;
         let txn  = state.conn.begin().await?;
 
-        let _ = SysRolePerm::delete_many()
+        let _ = SysRolePerm::soft_delete(Local::now().naive_local())
             .filter(sys_role_perm::Column::RoleId.eq(role_id))
             .exec(&txn)
             .await?;
@@ -43,11 +45,12 @@ impl PermissionService {
             .await?;
 
         txn.commit().await?;
+        UserService::clear_grants();
         Ok(())
     }
 
     pub async fn get_menus_permission_by_role_id(state:Data<AppState>, id:i32)->Result<Vec<String>,UserError> {
-        let vec = SysRolePerm::find()
+        let vec = SysRolePerm::active()
             .filter(sys_role_perm::Column::RoleId.eq(id))
             .all(&state.conn)
             .await?
@@ -0,0 +1,89 @@
+use actix_web::web::Data;
+use chrono::Duration;
+use log::warn;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, TransactionTrait};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono::Local;
+use crate::{AppState, UserError};
+use crate::common::config::REFRESH_TOKEN_TTL_DAYS;
+use crate::common::security::Security;
+use crate::entity::prelude::SysRefreshToken;
+use crate::entity::sys_refresh_token::{ActiveModel, Column, Model};
+use crate::service::session_service::SessionService;
+
+pub struct RefreshTokenService;
+
+impl RefreshTokenService {
+
+    /// 签发 refresh token，同一会话内轮换出的 token 属于同一家族（`family_id` 即会话 id）
+    pub async fn issue<C>(conn: &C, user_id: i32, family_id: String) -> Result<String, UserError>
+    where C: sea_orm::ConnectionTrait {
+        let token = Security::generate_opaque_token();
+        let now = Local::now().naive_local();
+        let model = ActiveModel {
+            id: NotSet,
+            user_id: Set(user_id),
+            family_id: Set(family_id),
+            token_hash: Set(Security::hash_token(&token)),
+            expires_at: Set(now + Duration::days(*REFRESH_TOKEN_TTL_DAYS)),
+            used_at: NotSet,
+            revoked_at: NotSet,
+            updated_at: NotSet,
+            created_at: Set(now),
+        };
+        model.insert(conn).await?;
+        Ok(token)
+    }
+
+    /// 使用 refresh token 换取新的 refresh token，旧 token 作废。
+    /// 已使用过的 token 再次出现视为泄露，整个家族及其会话全部吊销。
+    /// 返回被消费的 token 记录和新签发的 token
+    pub async fn rotate(state: Data<AppState>, token: String) -> Result<(Model, String), UserError> {
+        let txn = state.conn.begin().await?;
+        let option = SysRefreshToken::find()
+            .filter(Column::TokenHash.eq(Security::hash_token(&token)))
+            .one(&txn)
+            .await?;
+        let Some(model) = option else {
+            return Err(UserError::Unauthorized("invalid refresh token".to_string()));
+        };
+        if model.revoked_at.is_some() {
+            return Err(UserError::Unauthorized("refresh token is revoked".to_string()));
+        }
+        let now = Local::now().naive_local();
+        if model.expires_at < now {
+            return Err(UserError::Unauthorized("refresh token is expired".to_string()));
+        }
+        // 条件更新，保证并发情况下同一个 token 只能被使用一次
+        let result = SysRefreshToken::update_many()
+            .col_expr(Column::UsedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(model.id))
+            .filter(Column::UsedAt.is_null())
+            .exec(&txn)
+            .await?;
+        if result.rows_affected == 0 {
+            warn!("refresh token reuse detected, user {} family {}", model.user_id, model.family_id);
+            SessionService::revoke_with(&txn, model.family_id.clone()).await?;
+            txn.commit().await?;
+            return Err(UserError::Unauthorized("refresh token is reused".to_string()));
+        }
+        let new_token = Self::issue(&txn, model.user_id, model.family_id.clone()).await?;
+        txn.commit().await?;
+        Ok((model, new_token))
+    }
+
+    pub async fn revoke_family<C>(conn: &C, family_id: String) -> Result<u64, UserError>
+    where C: sea_orm::ConnectionTrait {
+        let now = Local::now().naive_local();
+        let result = SysRefreshToken::update_many()
+            .col_expr(Column::RevokedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::FamilyId.eq(family_id))
+            .filter(Column::RevokedAt.is_null())
+            .exec(conn)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
@@ -1,14 +1,17 @@
 use crate::entity::role::{ActiveModel, Model};
 use crate::{AppState, UserError};
-use actix_web::web::{Data, Json};
-use log::info;
-use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, DbErr, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QueryTrait};
+use actix_web::web::Data;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, DbErr, EntityTrait, NotSet, Order, QueryFilter, Select, TransactionTrait};
 use sea_orm::ActiveValue::Set;
 use sea_orm::sqlx::types::chrono::Local;
 use serde::{Deserialize, Serialize};
+use crate::common::pagination::{paginate, Sorting};
 use crate::common::result::{FilterParam, PageResult};
+use crate::common::soft_delete::SoftDelete;
 use crate::entity::role::Column;
-use crate::entity::prelude::{Role};
+use crate::entity::prelude::{Role, SysRolePerm, SysUserRole};
+use crate::entity::{sys_role_perm, sys_user_role};
+use crate::service::user_service::UserService;
 
 pub struct RoleService;
 
@@ -17,6 +20,8 @@ pub struct RoleService;
 pub struct CreateRoleDto {
     pub role_name: String,
     pub role_desc: String,
+    #[serde(default)]
+    pub require_mfa: bool,
 }
 
 #[derive(Serialize,Deserialize,Debug)]
@@ -39,12 +44,22 @@ pub struct UpdateRole {
     pub create_role_dto: CreateRoleDto,
 }
 
+const SORTING: Sorting<Column> = Sorting {
+    columns: &[
+        ("id", Column::Id),
+        ("roleName", Column::RoleName),
+        ("createdAt", Column::CreatedAt),
+    ],
+    default: &[(Column::Id, Order::Asc)],
+};
+
 impl RoleService {
     pub async fn create(state:Data<AppState>, dto: CreateRoleDto) ->Result<Model,UserError> {
         let model = ActiveModel {
             id: NotSet,
             role_name: Set(dto.role_name),
             role_desc: Set(Some(dto.role_desc)),
+            require_mfa: Set(dto.require_mfa),
             updated_at: NotSet,
             created_at: Set(Local::now().naive_local()),
             deleted_at: NotSet,
@@ -53,25 +68,28 @@ impl RoleService {
         Ok(x)
     }
 
-    pub async fn find_all(state:Data<AppState>, dto: FilterParam<SearchRoleDto>) ->Result<PageResult<Model>,DbErr>{
+    pub async fn find_all(state:Data<AppState>, dto: FilterParam<SearchRoleDto>) ->Result<PageResult<Model>,UserError>{
+        Self::search(state, Role::active(), dto).await
+    }
+
+    /// 回收站
+    pub async fn find_trash(state:Data<AppState>, dto: FilterParam<SearchRoleDto>) ->Result<PageResult<Model>,UserError>{
+        Self::search(state, Role::trashed(), dto).await
+    }
+
+    async fn search(state:Data<AppState>, select: Select<Role>, mut dto: FilterParam<SearchRoleDto>) ->Result<PageResult<Model>,UserError>{
         let mut condition = Condition::all();
-        if let Some(filter) = dto.filters {
+        if let Some(filter) = dto.filters.take() {
             if let Some(role_name) = filter.role_name {
                 condition = condition.add(Column::RoleName.contains(role_name));
             }
         }
-        let list = Role::find()
-            .filter(condition)
-            .all(&state.conn)
-            .await?;
-        Ok(
-            PageResult::new(0, 0, list.clone(), list.len() as u64)
-        )
+        paginate(&state.conn, select.filter(condition), &dto, &SORTING).await
     }
-    
+
 
     pub async fn find_one(state:Data<AppState>, id:i32) ->Result<Model,DbErr> {
-        let option = Role::find_by_id(id)
+        let option = Role::active_by_id(id)
             .one(&state.conn)
             .await?;
         if let Some(s) = option {
@@ -86,20 +104,93 @@ impl RoleService {
             id: Set(update_params.id),
             role_name: Set(update_params.create_role_dto.role_name),
             role_desc: Set(Some(update_params.create_role_dto.role_desc)),
+            require_mfa: Set(update_params.create_role_dto.require_mfa),
             updated_at: Set(Some(Local::now().naive_local())),
             created_at: NotSet,
             deleted_at: NotSet,
         };
-        let result = model.update(&state.conn).await?;
+        let result = Role::update(model)
+            .filter(Column::DeletedAt.is_null())
+            .exec(&state.conn)
+            .await?;
         Ok(result)
     }
 
+    /// 移入回收站，角色的权限和用户分配一起删除
     pub async fn delete(state:Data<AppState>,del_params :DelParams)->Result<u64,UserError> {
-        let result = Role::delete_many()
+        let now = Local::now().naive_local();
+        let txn = state.conn.begin().await?;
+        let result = Role::soft_delete(now)
+            .filter(Column::Id.is_in(del_params.ids.clone()))
+            .exec(&txn)
+            .await?;
+        SysRolePerm::soft_delete(now)
+            .filter(sys_role_perm::Column::RoleId.is_in(del_params.ids.clone()))
+            .exec(&txn)
+            .await?;
+        SysUserRole::soft_delete(now)
+            .filter(sys_user_role::Column::RoleId.is_in(del_params.ids))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        UserService::clear_grants();
+        Ok(result.rows_affected)
+    }
+
+    /// 从回收站恢复，同时恢复一起删除的权限和用户分配
+    pub async fn restore(state:Data<AppState>,del_params :DelParams)->Result<u64,UserError> {
+        let trashed = Role::trashed()
             .filter(Column::Id.is_in(del_params.ids))
-            .exec(&state.conn)
+            .all(&state.conn)
             .await?;
+        let txn = state.conn.begin().await?;
+        for role in &trashed {
+            let Some(deleted_at) = role.deleted_at else { continue };
+            Role::restore()
+                .filter(Column::DeletedAt.eq(deleted_at))
+                .filter(Column::Id.eq(role.id))
+                .exec(&txn)
+                .await?;
+            SysRolePerm::restore()
+                .filter(sys_role_perm::Column::DeletedAt.eq(deleted_at))
+                .filter(sys_role_perm::Column::RoleId.eq(role.id))
+                .exec(&txn)
+                .await?;
+            SysUserRole::restore()
+                .filter(sys_user_role::Column::DeletedAt.eq(deleted_at))
+                .filter(sys_user_role::Column::RoleId.eq(role.id))
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+        UserService::clear_grants();
+        Ok(trashed.len() as u64)
+    }
 
+    /// 彻底删除回收站中的角色及其权限和用户分配
+    pub async fn purge(state:Data<AppState>,del_params :DelParams)->Result<u64,UserError> {
+        let ids: Vec<i32> = Role::trashed()
+            .filter(Column::Id.is_in(del_params.ids))
+            .all(&state.conn)
+            .await?
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        let txn = state.conn.begin().await?;
+        SysRolePerm::delete_many()
+            .filter(sys_role_perm::Column::RoleId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+        SysUserRole::delete_many()
+            .filter(sys_user_role::Column::RoleId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+        let result = Role::purge()
+            .filter(Column::Id.is_in(ids))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        UserService::clear_grants();
         Ok(result.rows_affected)
     }
 }
\ No newline at end of file
@@ -0,0 +1,148 @@
+use actix_web::web::Data;
+use actix_web::HttpRequest;
+use actix_web::http::header::USER_AGENT;
+use chrono::Duration;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::sqlx::types::chrono::Local;
+use serde::{Deserialize, Serialize};
+use crate::{AppState, UserError};
+use crate::common::client_ip::client_ip;
+use crate::common::config::REFRESH_TOKEN_TTL_DAYS;
+use crate::entity::prelude::SysUserSession;
+use crate::entity::sys_user_session::{ActiveModel, Column, Model};
+use crate::service::refresh_token_service::RefreshTokenService;
+
+/// 登录会话，每次登录（每台设备）一条记录，refresh token 家族与会话一一对应
+pub struct SessionService;
+
+/// 登录时客户端的设备信息
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub device: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_request(req: &HttpRequest, device: Option<String>) -> Self {
+        ClientInfo {
+            device,
+            ip: client_ip(req),
+            user_agent: req.headers().get(USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDto {
+    pub current: bool,
+    #[serde(flatten)]
+    pub session: Model,
+}
+
+impl SessionService {
+
+    pub async fn create(state: Data<AppState>, user_id: i32, client: ClientInfo) -> Result<Model, UserError> {
+        let now = Local::now().naive_local();
+        let model = ActiveModel {
+            id: Set(uuid::Uuid::new_v4().to_string()),
+            user_id: Set(user_id),
+            device: Set(client.device),
+            ip: Set(client.ip),
+            user_agent: Set(client.user_agent),
+            last_seen_at: Set(Some(now)),
+            expires_at: Set(now + Duration::days(*REFRESH_TOKEN_TTL_DAYS)),
+            revoked_at: Set(None),
+            updated_at: Set(None),
+            created_at: Set(now),
+        };
+        let model = model.insert(&state.conn).await?;
+        Ok(model)
+    }
+
+    pub async fn find_active(state: Data<AppState>, id: &str) -> Result<Option<Model>, DbErr> {
+        SysUserSession::find_by_id(id.to_string())
+            .filter(Column::RevokedAt.is_null())
+            .filter(Column::ExpiresAt.gt(Local::now().naive_local()))
+            .one(&state.conn)
+            .await
+    }
+
+    /// 刷新 token 时更新最后活跃时间并顺延会话有效期
+    pub async fn touch(state: Data<AppState>, id: &str) -> Result<(), DbErr> {
+        let now = Local::now().naive_local();
+        SysUserSession::update_many()
+            .col_expr(Column::LastSeenAt, Expr::value(now))
+            .col_expr(Column::ExpiresAt, Expr::value(now + Duration::days(*REFRESH_TOKEN_TTL_DAYS)))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(id))
+            .exec(&state.conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_by_user(state: Data<AppState>, user_id: i32, current: &str) -> Result<Vec<SessionDto>, DbErr> {
+        let list = SysUserSession::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RevokedAt.is_null())
+            .filter(Column::ExpiresAt.gt(Local::now().naive_local()))
+            .order_by_desc(Column::LastSeenAt)
+            .all(&state.conn)
+            .await?
+            .into_iter()
+            .map(|session| SessionDto {
+                current: session.id == current,
+                session,
+            })
+            .collect();
+        Ok(list)
+    }
+
+    /// 吊销会话及其 refresh token，该会话签发的 access token 随之失效
+    pub async fn revoke_with<C>(conn: &C, id: String) -> Result<u64, UserError>
+    where C: ConnectionTrait {
+        let now = Local::now().naive_local();
+        let result = SysUserSession::update_many()
+            .col_expr(Column::RevokedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(id.clone()))
+            .filter(Column::RevokedAt.is_null())
+            .exec(conn)
+            .await?;
+        RefreshTokenService::revoke_family(conn, id).await?;
+        Ok(result.rows_affected)
+    }
+
+    /// 吊销用户的全部会话，用于重置密码等场景
+    pub async fn revoke_all<C>(conn: &C, user_id: i32) -> Result<u64, UserError>
+    where C: ConnectionTrait {
+        let sessions = SysUserSession::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RevokedAt.is_null())
+            .all(conn)
+            .await?;
+        let mut count = 0;
+        for session in sessions {
+            count += Self::revoke_with(conn, session.id).await?;
+        }
+        Ok(count)
+    }
+
+    /// 吊销当前用户的某个会话
+    pub async fn revoke(state: Data<AppState>, user_id: i32, id: String) -> Result<(), UserError> {
+        let option = SysUserSession::find_by_id(id.clone())
+            .filter(Column::UserId.eq(user_id))
+            .one(&state.conn)
+            .await?;
+        if option.is_none() {
+            return Err(UserError::DbErr(DbErr::RecordNotFound(id)));
+        }
+        Self::revoke_with(&state.conn, id).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use actix_web::web::Data;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::sqlx::types::chrono::Local;
+use crate::{AppState, UserError};
+use crate::common::security::Claims;
+use crate::entity::prelude::SysRevokedToken;
+use crate::entity::sys_revoked_token::{ActiveModel, Column};
+
+/// access token 吊销列表，按 JWT 的 `jti` 记录，过期后由定时任务清理
+pub struct TokenRevocationService;
+
+impl TokenRevocationService {
+
+    pub async fn revoke(state: Data<AppState>, claims: &Claims) -> Result<(), UserError> {
+        let model = ActiveModel {
+            jti: Set(claims.jti.clone()),
+            user_id: Set(claims.user_id()?),
+            expires_at: Set(claims.expires_at()),
+            created_at: Set(Local::now().naive_local()),
+        };
+        SysRevokedToken::insert(model)
+            .on_conflict(OnConflict::column(Column::Jti).do_nothing().to_owned())
+            .do_nothing()
+            .exec(&state.conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn is_revoked(state: Data<AppState>, jti: &str) -> Result<bool, DbErr> {
+        let option = SysRevokedToken::find_by_id(jti.to_string())
+            .one(&state.conn)
+            .await?;
+        Ok(option.is_some())
+    }
+
+    pub async fn purge_expired(conn: &DatabaseConnection) -> Result<u64, DbErr> {
+        let result = SysRevokedToken::delete_many()
+            .filter(Column::ExpiresAt.lt(Local::now().naive_local()))
+            .exec(conn)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
@@ -1,20 +1,37 @@
 use actix_web::web::Data;
-use argon2::PasswordVerifier;
 use log::info;
-use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, DbErr, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QuerySelect, QueryTrait, SelectColumns, TransactionTrait, TryIntoModel};
-use sea_orm::ActiveValue::{Set, Unchanged};
+use once_cell::sync::Lazy;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, NotSet, Order, QueryFilter, QueryOrder, QuerySelect, Select, TransactionTrait};
+use sea_orm::ActiveValue::Set;
 use sea_orm::prelude::DateTime;
-use sea_orm::sea_query::UnOper::Not;
-use sea_orm::sqlx::encode::IsNull::No;
+use sea_orm::sea_query::Expr;
 use sea_orm::sqlx::types::chrono::Local;
 use serde::{Deserialize, Serialize};
 use crate::entity::user::{ActiveModel, Column, Model};
 use crate::{AppState, UserError};
+use crate::common::config::{PASSWORD_HISTORY_SIZE, PERM_CACHE_SECS, USER_STATUS_CACHE_SECS};
+use crate::common::password_policy::PASSWORD_POLICY;
+use crate::common::pagination::{paginate, Sorting};
 use crate::common::result::{FilterParam, PageResult};
 use crate::common::security::Security;
-use crate::entity::prelude::{SysRolePerm, SysUserRole, User};
+use crate::common::soft_delete::SoftDelete;
+use crate::common::ttl_cache::TtlCache;
+use crate::entity::prelude::{SysPasswordHistory, SysRolePerm, SysUserIdentity, SysUserRole, User};
+use crate::entity::{sys_password_history, sys_user_identity, sys_user_role};
+use crate::service::login_attempt_service::LoginAttemptService;
 
 pub struct UserService;
+
+static USER_AVAILABLE: Lazy<TtlCache<i32, bool>> = Lazy::new(||
+    TtlCache::new(std::time::Duration::from_secs(*USER_STATUS_CACHE_SECS))
+);
+
+/// 用户的角色 id 和权限码
+pub type Grants = (Vec<i32>, Vec<String>);
+
+static USER_GRANTS: Lazy<TtlCache<i32, Grants>> = Lazy::new(||
+    TtlCache::new(std::time::Duration::from_secs(*PERM_CACHE_SECS))
+);
 #[derive(Debug,Serialize,Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateUser {
@@ -27,6 +44,10 @@ pub struct CreateUser {
     pub email: String,
     pub department_id: i32,
     pub role_id:Vec<i32>,
+    #[serde(default)]
+    pub service_account: bool,
+    #[serde(default)]
+    pub auth_provider: Option<String>,
 }
 
 #[derive(Debug,Serialize,Deserialize)]
@@ -41,6 +62,11 @@ pub struct UpdateUser {
 pub struct UserName {
     pub id:i32,
     pub password: String,
+    pub available: bool,
+    pub service_account: bool,
+    pub auth_provider: Option<String>,
+    /// 最后一次修改密码的时间，从未修改过时为创建时间
+    pub password_changed_at: DateTime,
 }
 
 #[derive(Debug,Serialize,Deserialize)]
@@ -66,40 +92,82 @@ pub struct UserDto {
 
 #[derive(Debug,Serialize,Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ChangePassword {
-    pub id: i32,
+pub struct ModifyPassword {
     pub new_password: String,
     pub old_password: String,
 }
 
+/// 用户可自行修改的资料
+#[derive(Debug,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProfile {
+    pub email: Option<String>,
+    pub mobile: Option<String>,
+    pub telephone: Option<String>,
+}
+
+const SORTING: Sorting<Column> = Sorting {
+    columns: &[
+        ("id", Column::Id),
+        ("userName", Column::UserName),
+        ("departmentId", Column::DepartmentId),
+        ("lastLoginTime", Column::LastLoginTime),
+        ("createdAt", Column::CreatedAt),
+    ],
+    default: &[(Column::Id, Order::Asc)],
+};
+
 impl UserService {
 
 
+    /// 指定的认证方式必须已启用
+    fn check_auth_provider(state:&Data<AppState>, auth_provider:Option<&str>) -> Result<(),UserError> {
+        match auth_provider {
+            Some(name) if state.auth_providers.find(name).is_none() => {
+                Err(UserError::ValidationError { field: "authProvider".to_string() })
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub async fn create_user(state:Data<AppState>,user: CreateUser) -> Result<Model,UserError> {
-        if user.password.is_none() {
-            return Err(UserError::Error(String::from("Invalid password")));
+        Self::check_auth_provider(&state, user.auth_provider.as_deref())?;
+        // 服务账号不用密码登录，未指定时使用随机密码
+        let raw_password = match user.password {
+            Some(password) => password,
+            None if user.service_account => Security::generate_opaque_token(),
+            None => return Err(UserError::Error(String::from("Invalid password"))),
+        };
+        if !user.service_account {
+            PASSWORD_POLICY.validate(&user.user_name, &raw_password)?;
         }
-        let password = Security::hash_password(user.password.unwrap().as_str())?;
+        let password = Security::hash_password(raw_password.as_str())?;
+        let now = Local::now().naive_local();
         let model = ActiveModel {
             id: NotSet,
             email: Set(Some(user.email)),
             user_name: Set(user.user_name),
-            password: Set(password),
+            password: Set(password.clone()),
             available: Set(user.available),
+            service_account: Set(user.service_account),
+            auth_provider: Set(user.auth_provider),
             sex: Set(user.sex),
             mobile: Set(user.mobile),
             telephone: Set(Some(user.telephone)),
             department_id: Set(user.department_id),
             last_login_time: NotSet,
+            last_login_ip: NotSet,
+            password_changed_at: Set(Some(now)),
             updated_at: NotSet,
-            created_at: Set(Local::now().naive_local()),
+            created_at: Set(now),
             deleted_at: NotSet,
         };
         let txn = state.conn.begin().await?;
         let x = model.insert(&txn).await?;
-        let user_role:Vec<crate::entity::sys_user_role::ActiveModel> = user.role_id.iter()
+        Self::add_password_history(&txn, x.id, password).await?;
+        let user_role:Vec<sys_user_role::ActiveModel> = user.role_id.iter()
             .map(|&m| {
-                crate::entity::sys_user_role::ActiveModel {
+                sys_user_role::ActiveModel {
                     id: NotSet,
                     role_id: Set(m),
                     user_id: Set(x.id),
@@ -116,7 +184,7 @@ impl UserService {
     }
 
     pub async fn find_one_by_user_name(state:Data<AppState>, user_name: String)->Result<UserName,DbErr> {
-        let option = User::find()
+        let option = User::active()
             .filter(Column::UserName.eq(&user_name))
             .one(&state.conn)
             .await?;
@@ -124,15 +192,28 @@ impl UserService {
             Ok(UserName {
                 id: user.id,
                 password: user.password,
+                available: user.available,
+                service_account: user.service_account,
+                auth_provider: user.auth_provider,
+                password_changed_at: user.password_changed_at.unwrap_or(user.created_at),
             })
         }else {
             Err(DbErr::RecordNotFound(user_name.clone()))
         }
     }
 
-    pub async fn find_all(state:Data<AppState>, page: FilterParam<SearchParams>) ->Result<PageResult<Model>,DbErr> {
+    pub async fn find_all(state:Data<AppState>, page: FilterParam<SearchParams>) ->Result<PageResult<Model>,UserError> {
+        Self::search(state, User::active(), page).await
+    }
+
+    /// 回收站
+    pub async fn find_trash(state:Data<AppState>, page: FilterParam<SearchParams>) ->Result<PageResult<Model>,UserError> {
+        Self::search(state, User::trashed(), page).await
+    }
+
+    async fn search(state:Data<AppState>, select: Select<User>, mut page: FilterParam<SearchParams>) ->Result<PageResult<Model>,UserError> {
         let mut conditions = Condition::all();
-        if let Some(f) = page.filters {
+        if let Some(f) = page.filters.take() {
             if let Some(user_name) = f.user_name {
                 conditions = conditions.add(Column::UserName.contains(user_name));
             }
@@ -140,15 +221,7 @@ impl UserService {
                 conditions = conditions.add(Column::DepartmentId.eq(department_id));
             }
         }
-        let paginator = User::find()
-            .filter(conditions)
-            .paginate(&state.conn, page.page_size);
-
-        let total = paginator.num_items().await?;
-        paginator.fetch_page(page.page_index-1)
-            .await.map(|list| {
-            PageResult::new(page.page_index,page.page_size,list,total)
-        })
+        paginate(&state.conn, select.filter(conditions), &page, &SORTING).await
     }
 
     pub async fn find_one(state:Data<AppState>,id:i32)->Result<UserDto, DbErr> {
@@ -156,15 +229,15 @@ impl UserService {
             role_id: vec![],
             result: None,
         };
-        let option = User::find_by_id(id)
+        let option = User::active_by_id(id)
             .one(&state.conn)
             .await?;
         if option.is_none() {
             return Err(DbErr::RecordNotFound(id.to_string()));
         }
         user_dto.result = Some(option.unwrap());
-        let vec:Vec<i32> = SysUserRole::find()
-            .filter(crate::entity::sys_user_role::Column::UserId.eq(id))
+        let vec:Vec<i32> = SysUserRole::active()
+            .filter(sys_user_role::Column::UserId.eq(id))
             .all(&state.conn)
             .await?
             .iter()
@@ -174,15 +247,17 @@ impl UserService {
         Ok(user_dto)
     }
 
-    pub async fn find_one_auth_code(state:Data<AppState>,id: i32) ->Result<Vec<String>,DbErr> {
-        info!("{:?}",id);
-        let roles = SysUserRole::find()
-            .filter(crate::entity::sys_user_role::Column::UserId.eq(id))
+    pub async fn find_role_ids(state:Data<AppState>,id: i32) ->Result<Vec<i32>,DbErr> {
+        let roles = SysUserRole::active()
+            .filter(sys_user_role::Column::UserId.eq(id))
             .all(&state.conn)
             .await?
             .iter().map(|m| m.role_id).collect::<Vec<_>>();
-        println!("{:?}",roles);
-        let vec = SysRolePerm::find()
+        Ok(roles)
+    }
+
+    pub async fn find_auth_code_by_roles(state:Data<AppState>,roles: Vec<i32>) ->Result<Vec<String>,DbErr> {
+        let vec = SysRolePerm::active()
             .filter(crate::entity::sys_role_perm::Column::RoleId.is_in(roles))
             .all(&state.conn)
             .await?
@@ -190,7 +265,31 @@ impl UserService {
         Ok(vec)
     }
 
+    /// 用户当前的角色和权限码，结果缓存 `PERM_CACHE_SECS` 秒
+    pub async fn find_grants(state:Data<AppState>, id:i32)->Result<Grants,DbErr> {
+        if let Some(grants) = USER_GRANTS.get(&id) {
+            return Ok(grants);
+        }
+        let roles = Self::find_role_ids(state.clone(), id).await?;
+        let perms = Self::find_auth_code_by_roles(state, roles.clone()).await?;
+        USER_GRANTS.set(id, (roles.clone(), perms.clone()));
+        Ok((roles, perms))
+    }
+
+    /// 角色分配或角色的权限变更后调用，变更不多，直接清空全部
+    pub fn clear_grants() {
+        USER_GRANTS.clear();
+    }
+
+    pub async fn find_one_auth_code(state:Data<AppState>,id: i32) ->Result<Vec<String>,DbErr> {
+        info!("{:?}",id);
+        let roles = Self::find_role_ids(state.clone(), id).await?;
+        println!("{:?}",roles);
+        Self::find_auth_code_by_roles(state, roles).await
+    }
+
     pub async fn update(state:Data<AppState>,update_user: UpdateUser)->Result<Model,UserError> {
+        Self::check_auth_provider(&state, update_user.user.auth_provider.as_deref())?;
         let txn = state.conn.begin().await?;
         let update_model = ActiveModel {
             id: Set(update_user.id),
@@ -198,25 +297,33 @@ impl UserService {
             user_name: Set(update_user.user.user_name),
             password: NotSet,
             available: Set(update_user.user.available),
+            service_account: NotSet,
+            auth_provider: Set(update_user.user.auth_provider),
             sex: Set(update_user.user.sex),
             mobile: Set(update_user.user.mobile),
             telephone: Set(Some(update_user.user.telephone)),
             department_id: Set(update_user.user.department_id),
             last_login_time: NotSet,
+            last_login_ip: NotSet,
+            password_changed_at: NotSet,
             updated_at: Set(Some(Local::now().naive_local())),
             created_at: NotSet,
             deleted_at: NotSet,
         };
-        let model = update_model.update(&txn).await?;
+        let model = User::update(update_model)
+            .filter(Column::DeletedAt.is_null())
+            .exec(&txn)
+            .await?;
+        USER_AVAILABLE.remove(&model.id);
 
-        SysUserRole::delete_many()
-            .filter(crate::entity::sys_user_role::Column::UserId.eq(update_user.id))
+        SysUserRole::soft_delete(Local::now().naive_local())
+            .filter(sys_user_role::Column::UserId.eq(update_user.id))
             .exec(&txn).await?;
 
-        let sys_user_role:Vec<crate::entity::sys_user_role::ActiveModel> = update_user.user.role_id
+        let sys_user_role:Vec<sys_user_role::ActiveModel> = update_user.user.role_id
             .iter()
             .map(|&m| {
-                crate::entity::sys_user_role::ActiveModel {
+                sys_user_role::ActiveModel {
                     id: NotSet,
                     role_id: Set(m),
                     user_id: Set(update_user.id),
@@ -232,46 +339,230 @@ impl UserService {
         }
 
         txn.commit().await?;
+        Self::clear_grants();
         Ok(model)
     }
 
-    pub async fn change_pwd(state:Data<AppState>, pwd:ChangePassword)->Result<(),UserError> {
-        let option = User::find_by_id(pwd.id)
+    /// 校验旧密码，失败与登录共用失败计数和锁定
+    pub async fn change_pwd(state:Data<AppState>, id:i32, pwd:ModifyPassword, ip:Option<&str>)->Result<(),UserError> {
+        let option = User::active_by_id(id)
             .one(&state.conn)
             .await?;
         if option.is_none() {
-           return Err(UserError::DbErr(DbErr::RecordNotFound(pwd.id.to_string())));
+           return Err(UserError::DbErr(DbErr::RecordNotFound(id.to_string())));
         }
-        let mut model = option.unwrap();
+        let model = option.unwrap();
+        let store = state.login_attempts.clone();
+        LoginAttemptService::check(store.as_ref(), &model.user_name, ip).await?;
         let verify = Security::verify(&model.password, &pwd.old_password);
         if !verify {
+            LoginAttemptService::record_failure(store.as_ref(), &model.user_name, ip).await?;
             return Err(UserError::Error("old password is invalid".to_string()));
         }
-        let new_pass = Security::hash_password(pwd.new_password.as_str())?;
+        LoginAttemptService::reset(store.as_ref(), &model.user_name).await?;
+        Self::set_password(&state.conn, &model, &pwd.new_password).await
+    }
+
+    /// 按密码策略校验新密码，不允许与最近 `PASSWORD_HISTORY_SIZE` 次的密码相同
+    pub async fn set_password<C>(conn:&C, user:&Model, new_password:&str)->Result<(),UserError>
+    where C: ConnectionTrait {
+        PASSWORD_POLICY.validate(&user.user_name, new_password)?;
+        let history = SysPasswordHistory::find()
+            .filter(sys_password_history::Column::UserId.eq(user.id))
+            .order_by_desc(sys_password_history::Column::Id)
+            .limit(*PASSWORD_HISTORY_SIZE)
+            .all(conn)
+            .await?;
+        let reused = std::iter::once(user.password.as_str())
+            .chain(history.iter().map(|h| h.password_hash.as_str()))
+            .any(|hash| Security::verify(hash, new_password));
+        if reused {
+            return Err(UserError::WeakPassword("password was used recently".to_string()));
+        }
+        let new_pass = Security::hash_password(new_password)?;
+        let now = Local::now().naive_local();
+        User::update_many()
+            .col_expr(Column::Password, Expr::value(new_pass.clone()))
+            .col_expr(Column::PasswordChangedAt, Expr::value(now))
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(user.id))
+            .exec(conn)
+            .await?;
+        Self::add_password_history(conn, user.id, new_pass).await?;
+        Ok(())
+    }
+
+    async fn add_password_history<C>(conn:&C, user_id:i32, password_hash:String)->Result<(),DbErr>
+    where C: ConnectionTrait {
+        let model = sys_password_history::ActiveModel {
+            id: NotSet,
+            user_id: Set(user_id),
+            password_hash: Set(password_hash),
+            created_at: Set(Local::now().naive_local()),
+        };
+        model.insert(conn).await?;
+        // 只保留最近的记录
+        let keep: Vec<i32> = SysPasswordHistory::find()
+            .filter(sys_password_history::Column::UserId.eq(user_id))
+            .order_by_desc(sys_password_history::Column::Id)
+            .limit(*PASSWORD_HISTORY_SIZE)
+            .all(conn)
+            .await?
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        SysPasswordHistory::delete_many()
+            .filter(sys_password_history::Column::UserId.eq(user_id))
+            .filter(sys_password_history::Column::Id.is_not_in(keep))
+            .exec(conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_profile(state:Data<AppState>, id:i32, profile:UpdateProfile)->Result<Model,UserError> {
         let active_model = ActiveModel {
-            id: Set(model.id),
-            email: NotSet,
+            id: Set(id),
+            email: profile.email.map_or(NotSet, |e| Set(Some(e))),
             user_name: NotSet,
-            password: Set(new_pass),
+            password: NotSet,
             available: NotSet,
+            service_account: NotSet,
+            auth_provider: NotSet,
             sex: NotSet,
-            mobile: NotSet,
-            telephone: NotSet,
+            mobile: profile.mobile.map_or(NotSet, Set),
+            telephone: profile.telephone.map_or(NotSet, |t| Set(Some(t))),
             department_id: NotSet,
             last_login_time: NotSet,
+            last_login_ip: NotSet,
+            password_changed_at: NotSet,
             updated_at: Set(Some(Local::now().naive_local())),
             created_at: NotSet,
             deleted_at: NotSet,
         };
-        let _ = active_model.update(&state.conn).await?;
+        let model = active_model.update(&state.conn).await?;
+        Ok(model)
+    }
+
+    /// 记录最后登录时间和 IP
+    pub async fn record_login(state:Data<AppState>, id:i32, ip:Option<String>)->Result<(),DbErr> {
+        let now = Local::now().naive_local();
+        User::update_many()
+            .col_expr(Column::LastLoginTime, Expr::value(now))
+            .col_expr(Column::LastLoginIp, Expr::value(ip))
+            .filter(Column::Id.eq(id))
+            .exec(&state.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 登录时把旧参数或 bcrypt 的哈希升级为当前参数，不算修改密码
+    pub async fn upgrade_password_hash(state:Data<AppState>, id:i32, password:&str)->Result<(),UserError> {
+        let new_pass = Security::hash_password(password)?;
+        User::update_many()
+            .col_expr(Column::Password, Expr::value(new_pass))
+            .filter(Column::Id.eq(id))
+            .exec(&state.conn)
+            .await?;
         Ok(())
     }
 
+    /// 用户是否启用，结果缓存 `USER_STATUS_CACHE_SECS` 秒
+    pub async fn is_available(state:Data<AppState>, id:i32)->Result<bool,DbErr> {
+        if let Some(available) = USER_AVAILABLE.get(&id) {
+            return Ok(available);
+        }
+        let available = User::active_by_id(id)
+            .one(&state.conn)
+            .await?
+            .map(|m| m.available)
+            .unwrap_or(false);
+        USER_AVAILABLE.set(id, available);
+        Ok(available)
+    }
+
+    /// 解除因登录失败导致的账号锁定
+    pub async fn unlock(state:Data<AppState>, id:i32)->Result<(),UserError> {
+        let option = User::active_by_id(id)
+            .one(&state.conn)
+            .await?;
+        let Some(model) = option else {
+            return Err(UserError::DbErr(DbErr::RecordNotFound(id.to_string())));
+        };
+        LoginAttemptService::reset(state.login_attempts.as_ref(), &model.user_name).await
+    }
+
+    /// 移入回收站，用户的角色分配一起删除，已签发的 token 随即失效
     pub async fn delete(state:Data<AppState>,ids:DeleteParam)->Result<u64,DbErr> {
-        let result = User::delete_many()
+        let now = Local::now().naive_local();
+        let txn = state.conn.begin().await?;
+        let result = User::soft_delete(now)
+            .filter(Column::Id.is_in(ids.ids.clone()))
+            .exec(&txn)
+            .await?;
+        SysUserRole::soft_delete(now)
+            .filter(sys_user_role::Column::UserId.is_in(ids.ids.clone()))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Self::clear_grants();
+        for id in &ids.ids {
+            USER_AVAILABLE.remove(id);
+        }
+        Ok(result.rows_affected)
+    }
+
+    /// 从回收站恢复，同时恢复一起删除的角色分配
+    pub async fn restore(state:Data<AppState>,ids:DeleteParam)->Result<u64,DbErr> {
+        let trashed = User::trashed()
             .filter(Column::Id.is_in(ids.ids))
-            .exec(&state.conn)
+            .all(&state.conn)
             .await?;
+        let txn = state.conn.begin().await?;
+        for user in &trashed {
+            let Some(deleted_at) = user.deleted_at else { continue };
+            User::restore()
+                .filter(Column::DeletedAt.eq(deleted_at))
+                .filter(Column::Id.eq(user.id))
+                .exec(&txn)
+                .await?;
+            SysUserRole::restore()
+                .filter(sys_user_role::Column::DeletedAt.eq(deleted_at))
+                .filter(sys_user_role::Column::UserId.eq(user.id))
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+        Self::clear_grants();
+        for user in &trashed {
+            USER_AVAILABLE.remove(&user.id);
+        }
+        Ok(trashed.len() as u64)
+    }
+
+    /// 彻底删除回收站中的用户及其角色分配和外部身份
+    pub async fn purge(state:Data<AppState>,ids:DeleteParam)->Result<u64,DbErr> {
+        let ids: Vec<i32> = User::trashed()
+            .filter(Column::Id.is_in(ids.ids))
+            .all(&state.conn)
+            .await?
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        let txn = state.conn.begin().await?;
+        SysUserRole::delete_many()
+            .filter(sys_user_role::Column::UserId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+        SysUserIdentity::delete_many()
+            .filter(sys_user_identity::Column::UserId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+        let result = User::purge()
+            .filter(Column::Id.is_in(ids))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Self::clear_grants();
         Ok(result.rows_affected)
     }
 